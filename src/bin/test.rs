@@ -12,26 +12,67 @@ use core::{
     panic::PanicInfo,
     ptr,
     slice,
+    sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+    time::Duration,
 };
 
 use efi::{
     boot_services,
+    fb_console::{
+        FbConsole,
+        Font,
+    },
+    fs,
+    image::Image,
+    io::{
+        BufRead,
+        BufReader,
+        Cursor,
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+    },
+    partition::{
+        Gpt,
+        Mbr,
+    },
     boot_services::{
         AllocateType,
         Event,
         EventType,
+        Guid,
         OpenProtocolAttributes,
         Protocol,
+        ProtocolInterface,
         SearchType,
+        Timer,
         TPL,
+        TplGuard,
+        critical_section,
     },
     protocols::{
+        Attribute,
+        BlockIO,
+        BlockStream,
+        BltPixel,
+        Color,
+        ComponentName2,
+        DiskIO,
+        DiskStream,
         File,
         FileMode,
         FileAttributes,
+        FileInfo,
         FileSystemInfo,
+        FileSystemVolumeLabel,
+        GraphicsOutput,
         SimpleFileSystem,
         SimpleTextInput,
+        SimpleTextInputEx,
     },
     SystemTable,
     types::{
@@ -41,6 +82,7 @@ use efi::{
         PhysicalAddress,
         Status,
     },
+    volume::Volume,
 };
 
 
@@ -84,18 +126,98 @@ fn test_utf16_conversion(system_table: &SystemTable) -> Result<(), usize> {
 }
 
 
+fn test_console(system_table: &SystemTable) -> Result<(), usize> {
+
+    let mut num_errs = 0;
+    efi_println!(system_table, "test console");
+
+    efi_println!(system_table, "    test output string outside BMP");
+    match system_table.con_out.output_string("#   \u{1f600} \u{10348}\r\n") {
+        Ok(status) => {
+            efi_println!(system_table, "#   status: {:?}", status);
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to output string");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test output string across chunk boundary");
+    let mut long_string = [b'x'; 300];
+    long_string[298] = b'\r';
+    long_string[299] = b'\n';
+    let long_string = core::str::from_utf8(&long_string).unwrap();
+    if let Err(err) = system_table.con_out.output_string(long_string) {
+        efi_println!(system_table, "!   failed to output long string");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test enumerate modes");
+    for (mode_number, desc) in system_table.con_out.modes() {
+        efi_println!(system_table, "#   mode {}: {:?}", mode_number, desc);
+    }
+    match system_table.con_out.best_mode() {
+        Some((mode_number, desc)) => {
+            efi_println!(system_table, "#   best mode {}: {:?}", mode_number, desc);
+        },
+        None => {
+            efi_println!(system_table, "!   no supported modes");
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test save and restore attribute");
+    let saved = system_table.con_out.attribute();
+    let res = system_table.con_out.set_attribute(Attribute::new(Color::Yellow, Color::Blue))
+        .and_then(|_| system_table.con_out.set_attribute(saved));
+    if let Err(err) = res {
+        efi_println!(system_table, "!   failed to set attribute");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test read key with timeout");
+    let res = system_table.con_in.read_key_timeout(
+        &system_table.boot_services,
+        Duration::from_millis(100)
+    );
+    match res {
+        Ok(key) => {
+            efi_println!(system_table, "#   key: {:?}", key);
+        },
+        Err(Status::Timeout) => { },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to read key with timeout");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    if num_errs > 0 {
+        Err(num_errs)
+    } else {
+        Ok(())
+    }
+}
+
+
 fn test_events(system_table: &SystemTable) -> Result<(), usize> {
 
     let mut num_errs = 0;
     efi_println!(system_table, "test events");
 
     efi_println!(system_table, "    test creating simple event");
-    let simple_result = system_table.boot_services.create_event(
-        EventType::empty(),
-        TPL::Callback,
-        empty_callback,
-        &()
-    );
+    // The context is static, so it outlives the event
+    let simple_result = unsafe {
+        system_table.boot_services.create_event(
+            EventType::empty(),
+            TPL::Callback,
+            empty_callback,
+            &()
+        )
+    };
     match simple_result {
         Ok(simple_event) => {
 
@@ -131,12 +253,14 @@ fn test_events(system_table: &SystemTable) -> Result<(), usize> {
     }
 
     efi_println!(system_table, "    test creating event with callback");
-    let simple_result = system_table.boot_services.create_event(
-        EventType::NOTIFY_SIGNAL,
-        TPL::Callback,
-        echo_callback,
-        &"callback message"
-    );
+    let simple_result = unsafe {
+        system_table.boot_services.create_event(
+            EventType::NOTIFY_SIGNAL,
+            TPL::Callback,
+            echo_callback,
+            &"callback message"
+        )
+    };
     match simple_result {
         Ok(simple_event) => {
 
@@ -155,6 +279,159 @@ fn test_events(system_table: &SystemTable) -> Result<(), usize> {
         },
     }
 
+    efi_println!(system_table, "    test event with closure callback");
+    NOTIFICATIONS.store(0, Ordering::SeqCst);
+    let res = system_table.boot_services.create_owned_event(
+        EventType::NOTIFY_SIGNAL,
+        TPL::Callback,
+        None,
+        |_| { NOTIFICATIONS.fetch_add(1, Ordering::SeqCst); }
+    )
+        .and_then(|event| {
+            system_table.boot_services.signal_event(&event)?;
+            event.close()
+        });
+    match res {
+        Ok(()) if NOTIFICATIONS.load(Ordering::SeqCst) == 1 => { },
+        Ok(()) => {
+            let count = NOTIFICATIONS.load(Ordering::SeqCst);
+            efi_println!(system_table, "!   closure was called {} times", count);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to create event with closure callback");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test critical section");
+    NOTIFICATIONS.store(0, Ordering::SeqCst);
+    let res = system_table.boot_services.create_owned_event(
+        EventType::NOTIFY_SIGNAL,
+        TPL::Notify,
+        None,
+        |_| { NOTIFICATIONS.fetch_add(1, Ordering::SeqCst); }
+    )
+        .and_then(|event| {
+            let deferred = critical_section(&system_table.boot_services, || {
+                system_table.boot_services.signal_event(&event)
+                    .map(|_| NOTIFICATIONS.load(Ordering::SeqCst))
+            })?;
+            Ok((deferred, NOTIFICATIONS.load(Ordering::SeqCst)))
+        });
+    match res {
+        Ok((0, 1)) => { },
+        Ok((deferred, after)) => {
+            efi_println!(
+                system_table,
+                "!   wrong notification counts: {} during, {} after",
+                deferred,
+                after
+            );
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to test critical section");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test lowering task priority level");
+    let res = TplGuard::new(&system_table.boot_services, TPL::Notify)
+        .and_then(|_guard| {
+            TplGuard::new(&system_table.boot_services, TPL::Callback).map(|_| ())
+        });
+    match res {
+        Err(Status::InvalidParameter) => { },
+        Ok(()) => {
+            efi_println!(system_table, "!   lowered task priority level with a guard");
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to raise task priority level");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test stall");
+    if let Err(err) = system_table.boot_services.stall(Duration::from_millis(1)) {
+        efi_println!(system_table, "!   failed to stall");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test timers");
+    let bs = &*system_table.boot_services;
+    let res = Timer::one_shot(bs, Duration::from_millis(10))
+        .and_then(|timer| timer.wait())
+        .and_then(|_| Timer::periodic(bs, Duration::from_millis(10)))
+        .and_then(|timer| {
+            timer.wait()?;
+            timer.wait()?;
+            timer.cancel()
+        });
+    if let Err(err) = res {
+        efi_println!(system_table, "!   failed to wait for timers");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test wait with timeout");
+    let res = bs.create_owned_event(EventType::empty(), TPL::Callback, None, |_| { })
+        .and_then(|event| bs.wait_for_event_timeout(&[&event], Duration::from_millis(10)));
+    match res {
+        Err(Status::Timeout) => { },
+        Ok(index) => {
+            efi_println!(system_table, "!   unsignaled event {} was reported", index);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to wait with timeout");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test event groups");
+    let group = Some(&TEST_EVENT_GROUP_GUID);
+    let create = || unsafe {
+        bs.create_event_ex(EventType::empty(), TPL::Callback, empty_callback, &(), group)
+    };
+    let res = create()
+        .and_then(|first| {
+            let res = create()
+                .and_then(|second| {
+                    let res = bs.signal_event(first)
+                        .and_then(|_| bs.check_event(second));
+                    bs.close_event(second)?;
+                    res
+                });
+            bs.close_event(first)?;
+            res
+        });
+    if let Err(err) = res {
+        efi_println!(system_table, "!   event in group was not signaled with its group");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test join exit boot services group");
+    let res = bs.create_owned_event(
+        EventType::NOTIFY_SIGNAL,
+        TPL::Notify,
+        Some(&boot_services::EVENT_GROUP_EXIT_BOOT_SERVICES),
+        |_| { }
+    )
+        .and_then(|event| event.close());
+    if let Err(err) = res {
+        efi_println!(system_table, "!   failed to join exit boot services group");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
     if num_errs > 0 {
         Err(num_errs)
     } else {
@@ -292,6 +569,640 @@ fn test_protocols(image_handle: Handle, system_table: &SystemTable) -> Result<()
         },
     }
 
+    efi_println!(system_table, "    test list protocols on all handles");
+    let res = system_table.boot_services.locate_handle_buffer(SearchType::AllHandles, None, None)
+        .and_then(|handles| {
+            let mut num_protocols = 0;
+            for handle in handles.iter() {
+                num_protocols += system_table.boot_services.protocols_per_handle(*handle)?.len();
+            }
+            Ok((handles.len(), num_protocols))
+        });
+    match res {
+        Ok((num_handles, num_protocols)) => {
+            efi_println!(
+                system_table,
+                "#   found {} protocols on {} handles",
+                num_protocols,
+                num_handles
+            );
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to list protocols on all handles");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test locate protocol");
+    if let Err(err) = system_table.boot_services.locate_protocol::<SimpleTextInput>() {
+        efi_println!(system_table, "!   failed to locate protocol");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test open extended text input");
+    let res = system_table.boot_services.open_protocol::<SimpleTextInputEx>(
+        system_table.console_in_handle,
+        image_handle,
+        0,
+        OpenProtocolAttributes::GET_PROTOCOL
+    );
+    match res {
+        Ok(input) => {
+            efi_println!(system_table, "    test read extended key stroke");
+            match input.read_key_stroke_ex() {
+                Ok(key_data) => {
+                    efi_println!(system_table, "#   key data: {:?}", key_data);
+                },
+                Err(Status::NotReady) => { },
+                Err(err) => {
+                    efi_println!(system_table, "!   failed to read extended key stroke");
+                    efi_println!(system_table, "!   {:?}", err);
+                    num_errs += 1;
+                },
+            }
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to open extended text input");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test install protocol interface");
+    let bs = &*system_table.boot_services;
+    let res = bs.install_protocol_interface(None, &TEST_PROTOCOL)
+        .and_then(|handle| {
+            let interface = bs.open_protocol::<TestProtocol>(
+                handle,
+                image_handle,
+                0,
+                OpenProtocolAttributes::GET_PROTOCOL
+            )?;
+            let value = interface.value;
+            bs.uninstall_protocol_interface(handle, &TEST_PROTOCOL)?;
+            Ok(value)
+        });
+    match res {
+        Ok(42) => { },
+        Ok(value) => {
+            efi_println!(system_table, "!   installed interface has wrong value: {}", value);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to install protocol interface");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test install multiple protocol interfaces");
+    let interfaces = [ProtocolInterface::new(&TEST_PROTOCOL)];
+    let res = bs.install_multiple_protocol_interfaces(None, &interfaces)
+        .and_then(|handle| bs.uninstall_multiple_protocol_interfaces(handle, &interfaces));
+    if let Err(err) = res {
+        efi_println!(system_table, "!   failed to install multiple protocol interfaces");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test scoped protocols");
+    let res = bs.handle_protocol::<SimpleTextInput>(system_table.console_in_handle, image_handle)
+        .and_then(|_| bs.install_protocol_interface(None, &TEST_PROTOCOL))
+        .and_then(|handle| {
+            let conflict = {
+                let _first = bs.open_protocol_exclusive::<TestProtocol>(
+                    handle,
+                    image_handle,
+                    0,
+                    false
+                )?;
+                let entries = bs.open_protocol_information::<TestProtocol>(handle)?;
+                if entries.len() != 1 || entries[0].agent_handle != image_handle {
+                    efi_println!(system_table, "!   wrong open protocol information");
+                    efi_println!(system_table, "!   {:?}", entries);
+                    num_errs += 1;
+                }
+                bs.open_protocol_exclusive::<TestProtocol>(handle, image_handle, 0, false).err()
+            };
+            let reopened = bs.open_protocol_exclusive::<TestProtocol>(
+                handle,
+                image_handle,
+                0,
+                false
+            ).map(|_| ());
+            bs.uninstall_protocol_interface(handle, &TEST_PROTOCOL)?;
+            reopened.map(|_| conflict)
+        });
+    match res {
+        Ok(Some(Status::AccessDenied)) => { },
+        Ok(conflict) => {
+            efi_println!(system_table, "!   exclusive open did not conflict: {:?}", conflict);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to open scoped protocols");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test protocol notifications");
+    let res = bs.create_owned_event(EventType::NOTIFY_SIGNAL, TPL::Callback, None, |_| { })
+        .and_then(|event| {
+            let res = bs.register_protocol_notify::<TestProtocol>(&event)
+                .and_then(|registration| {
+                    let handle = bs.install_protocol_interface(None, &TEST_PROTOCOL)?;
+                    let notified = registration.next_handle(bs);
+                    bs.uninstall_protocol_interface(handle, &TEST_PROTOCOL)?;
+                    Ok(notified? == Some(handle))
+                });
+            event.close()?;
+            res
+        });
+    match res {
+        Ok(true) => { },
+        Ok(false) => {
+            efi_println!(system_table, "!   installed handle was not reported");
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to register for protocol notifications");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test get driver names");
+    let guid = ComponentName2::guid();
+    match bs.locate_handle(SearchType::ByProtocol, Some(guid), None) {
+        Ok(handles) => {
+            efi_println!(system_table, "#   found {} drivers with names", handles.len());
+            let res = bs.open_protocol::<ComponentName2>(
+                handles[0],
+                image_handle,
+                0,
+                OpenProtocolAttributes::GET_PROTOCOL
+            )
+                .and_then(|component_name| {
+                    let name = component_name.driver_name(b"en\0")?;
+                    boot_services::utf16_to_str(name, bs)
+                });
+            match res {
+                Ok(name) => efi_println!(system_table, "#   driver name: {}", name),
+                Err(err) => {
+                    efi_println!(system_table, "!   failed to get driver name");
+                    efi_println!(system_table, "!   {:?}", err);
+                    num_errs += 1;
+                },
+            }
+        },
+        Err(Status::NotFound) => { },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to locate drivers with names");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    if num_errs > 0 {
+        Err(num_errs)
+    } else {
+        Ok(())
+    }
+}
+
+
+fn test_graphics(image_handle: Handle, system_table: &SystemTable) -> Result<(), usize> {
+
+    let mut num_errs = 0;
+    efi_println!(system_table, "test graphics");
+
+    efi_println!(system_table, "    test locate graphics output");
+    let guid = GraphicsOutput::guid();
+    let res = system_table.boot_services.locate_handle(SearchType::ByProtocol, Some(guid), None);
+    let handles = match res {
+        Ok(handles) => handles,
+        Err(err) => {
+            efi_println!(system_table, "!   failed to locate graphics output");
+            efi_println!(system_table, "!   {:?}", err);
+            return Err(1);
+        },
+    };
+
+    let res = system_table.boot_services.open_protocol::<GraphicsOutput>(
+        handles[0],
+        image_handle,
+        0,
+        OpenProtocolAttributes::GET_PROTOCOL
+    );
+    match res {
+        Ok(gop) => {
+            efi_println!(system_table, "    test enumerate graphics modes");
+            for (mode_number, info) in gop.modes(&system_table.boot_services) {
+                efi_println!(
+                    system_table,
+                    "#   mode {}: {}x{} {:?}",
+                    mode_number,
+                    info.horizontal_resolution,
+                    info.vertical_resolution,
+                    info.pixel_format
+                );
+            }
+
+            efi_println!(system_table, "    test blt video fill and read back");
+            let color = BltPixel::new(0x20, 0x40, 0x80);
+            let mut buf = [BltPixel::default(); 16];
+            let res = gop.blt_video_fill(color, (0, 0), (4, 4))
+                .and_then(|_| gop.blt_video_to_buffer(&mut buf, 4, (0, 0), (0, 0), (4, 4)));
+            match res {
+                Ok(()) => {
+                    let same_color = |p: &BltPixel| {
+                        p.red == color.red && p.green == color.green && p.blue == color.blue
+                    };
+                    if !buf.iter().all(same_color) {
+                        efi_println!(system_table, "!   read back unexpected pixels");
+                        num_errs += 1;
+                    }
+                },
+                Err(err) => {
+                    efi_println!(system_table, "!   failed to blt");
+                    efi_println!(system_table, "!   {:?}", err);
+                    num_errs += 1;
+                },
+            }
+
+            efi_println!(system_table, "    test framebuffer access");
+            match gop.frame_buffer() {
+                Ok(mut fb) => {
+                    let color = BltPixel::new(0xff, 0x00, 0x00);
+                    fb.write_pixel(0, 0, color);
+                    if fb.read_pixel(0, 0) != Some(color) {
+                        efi_println!(system_table, "!   framebuffer pixel mismatch");
+                        num_errs += 1;
+                    }
+
+                    efi_println!(system_table, "    test framebuffer console");
+                    let mut console = FbConsole::new(fb, Font::builtin(), 2);
+                    console.set_cursor_position(0, console.rows() - 1);
+                    let (columns, rows) = (console.columns(), console.rows());
+                    efi_println!(console, "framebuffer console: {}x{}", columns, rows);
+                },
+                Err(Status::Unsupported) => { },
+                Err(err) => {
+                    efi_println!(system_table, "!   failed to get framebuffer");
+                    efi_println!(system_table, "!   {:?}", err);
+                    num_errs += 1;
+                },
+            }
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to open graphics output");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    if num_errs > 0 {
+        Err(num_errs)
+    } else {
+        Ok(())
+    }
+}
+
+
+fn test_images(system_table: &SystemTable) -> Result<(), usize> {
+
+    let mut num_errs = 0;
+    efi_println!(system_table, "test images");
+
+    let red = BltPixel::new(0xff, 0x00, 0x00);
+    let green = BltPixel::new(0x00, 0xff, 0x00);
+    let blue = BltPixel::new(0x00, 0x00, 0xff);
+    let white = BltPixel::new(0xff, 0xff, 0xff);
+
+    efi_println!(system_table, "    test decode BMP");
+    // 2x2 24-bit BMP with red and green on the top row, blue and white on the bottom row
+    let bmp: [u8; 70] = [
+        0x42, 0x4d, 0x46, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x36, 0x00, 0x00, 0x00,
+        0x28, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0xff, 0x00, 0x00, 0xff, 0xff, 0xff, 0x00, 0x00,
+        0x00, 0x00, 0xff, 0x00, 0xff, 0x00, 0x00, 0x00,
+    ];
+    match Image::decode(&bmp, &system_table.boot_services) {
+        Ok(image) => {
+            let expected = [red, green, blue, white];
+            if image.width() != 2 || image.height() != 2 || !same_colors(image.pixels(), &expected) {
+                efi_println!(system_table, "!   decoded unexpected BMP pixels");
+                num_errs += 1;
+            }
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to decode BMP");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test decode QOI");
+    // 2x2 QOI using the RGB, INDEX and RUN operations
+    let qoi: [u8; 32] = [
+        0x71, 0x6f, 0x69, 0x66, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x03, 0x00,
+        0xfe, 0xff, 0x00, 0x00,
+        0xfe, 0x00, 0xff, 0x00,
+        0x32,
+        0xc0,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    ];
+    match Image::decode(&qoi, &system_table.boot_services) {
+        Ok(image) => {
+            let expected = [red, green, red, red];
+            if image.width() != 2 || image.height() != 2 || !same_colors(image.pixels(), &expected) {
+                efi_println!(system_table, "!   decoded unexpected QOI pixels");
+                num_errs += 1;
+            }
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to decode QOI");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    if num_errs > 0 {
+        Err(num_errs)
+    } else {
+        Ok(())
+    }
+}
+
+
+fn same_colors(actual: &[BltPixel], expected: &[BltPixel]) -> bool {
+
+    actual.len() == expected.len() && actual.iter().zip(expected).all(|(a, e)| {
+        a.red == e.red && a.green == e.green && a.blue == e.blue
+    })
+}
+
+
+fn test_io(system_table: &SystemTable) -> Result<(), usize> {
+
+    let mut num_errs = 0;
+    efi_println!(system_table, "test io");
+
+    efi_println!(system_table, "    test write and seek cursor");
+    let mut cursor = Cursor::new([0u8; 16]);
+    let res = cursor.write_all(b"first\nsecond\n")
+        .and_then(|_| cursor.seek(SeekFrom::End(-3)))
+        .and_then(|_| cursor.seek(SeekFrom::Current(-8)));
+    match res {
+        Ok(5) => { },
+        Ok(pos) => {
+            efi_println!(system_table, "!   cursor at wrong position: {}", pos);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to write and seek cursor");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+    if cursor.write_all(&[0; 12]) != Err(Status::VolumeFull) {
+        efi_println!(system_table, "!   write past end of cursor did not fail");
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test buffered lines");
+    let mut buf = [0u8; 4];
+    let mut reader = BufReader::new(&b"first\nsecond"[..], &mut buf);
+    let mut line = [0u8; 16];
+    let res = reader.read_until(b'\n', &mut line).and_then(|first| {
+        reader.read_until(b'\n', &mut line[first..]).map(|second| first + second)
+    });
+    match res {
+        Ok(12) if &line[..12] == b"first\nsecond" => { },
+        Ok(len) => {
+            efi_println!(system_table, "!   read wrong lines: {:?}", &line[..len]);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to read lines");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test read exact");
+    let mut data = &b"abc"[..];
+    let mut out = [0u8; 4];
+    if data.read_exact(&mut out) != Err(Status::EndOfFile) {
+        efi_println!(system_table, "!   short read exact did not fail");
+        num_errs += 1;
+    }
+
+    if num_errs > 0 {
+        Err(num_errs)
+    } else {
+        Ok(())
+    }
+}
+
+
+fn test_block_io(image_handle: Handle, system_table: &SystemTable) -> Result<(), usize> {
+
+    let mut num_errs = 0;
+    efi_println!(system_table, "test block io");
+
+    efi_println!(system_table, "    test enumerating block devices");
+    let guid = BlockIO::guid();
+    let res = system_table.boot_services.locate_handle(SearchType::ByProtocol, Some(guid), None);
+    let handles = match res {
+        Ok(handles) => handles,
+        Err(err) => {
+            efi_println!(system_table, "!   failed to locate block devices");
+            efi_println!(system_table, "!   {:?}", err);
+            return Err(1);
+        },
+    };
+
+    for handle in handles.iter() {
+        let res = system_table.boot_services.open_protocol::<BlockIO>(
+            *handle,
+            image_handle,
+            0,
+            OpenProtocolAttributes::GET_PROTOCOL
+        );
+        let block_io = match res {
+            Ok(block_io) => block_io,
+            Err(err) => {
+                efi_println!(system_table, "!   failed to open block io protocol");
+                efi_println!(system_table, "!   {:?}", err);
+                num_errs += 1;
+                continue;
+            },
+        };
+
+        let media = &*block_io.media;
+        efi_println!(
+            system_table,
+            "#   media {}: block size {}, last block {}, partition {:?}",
+            media.media_id,
+            media.block_size,
+            media.last_block,
+            media.logical_partition
+        );
+        if !bool::from(media.media_present) {
+            continue;
+        }
+
+        efi_println!(system_table, "    test read first block");
+        match system_table.boot_services.allocate_slice::<u8>(media.block_size as usize) {
+            Ok(mut buf) => {
+                if let Err(err) = block_io.read_blocks(media.media_id, 0, &mut buf) {
+                    efi_println!(system_table, "!   failed to read block");
+                    efi_println!(system_table, "!   {:?}", err);
+                    num_errs += 1;
+                }
+            },
+            Err(err) => {
+                efi_println!(system_table, "!   failed to allocate block buffer");
+                efi_println!(system_table, "!   {:?}", err);
+                num_errs += 1;
+            },
+        }
+
+        efi_println!(system_table, "    test disk stream");
+        let res = system_table.boot_services.open_protocol::<DiskIO>(
+            *handle,
+            image_handle,
+            0,
+            OpenProtocolAttributes::GET_PROTOCOL
+        );
+        match res {
+            Ok(disk_io) => {
+                let mut stream = DiskStream::from_media(&*disk_io, media);
+                let mut buf = [0u8; 16];
+                stream.set_position(stream.len() - 8);
+                match stream.read(&mut buf) {
+                    Ok(8) => {
+                        if stream.position() != stream.len() {
+                            efi_println!(system_table, "!   wrong position after read");
+                            num_errs += 1;
+                        }
+                    },
+                    Ok(len) => {
+                        efi_println!(system_table, "!   read {} bytes at end of disk", len);
+                        num_errs += 1;
+                    },
+                    Err(err) => {
+                        efi_println!(system_table, "!   failed to read disk");
+                        efi_println!(system_table, "!   {:?}", err);
+                        num_errs += 1;
+                    },
+                }
+            },
+            Err(err) => {
+                efi_println!(system_table, "!   failed to open disk io protocol");
+                efi_println!(system_table, "!   {:?}", err);
+                num_errs += 1;
+            },
+        }
+
+        if bool::from(media.logical_partition) {
+            continue;
+        }
+
+        efi_println!(system_table, "    test read partition table");
+        let mut disk = match BlockStream::new(&block_io, &system_table.boot_services) {
+            Ok(disk) => disk,
+            Err(err) => {
+                efi_println!(system_table, "!   failed to create block stream");
+                efi_println!(system_table, "!   {:?}", err);
+                num_errs += 1;
+                continue;
+            },
+        };
+        match Mbr::read(&mut disk) {
+            Ok(ref mbr) if mbr.is_protective() => {
+                match Gpt::read(&mut disk, media.block_size as usize) {
+                    Ok(gpt) => {
+                        for partition in gpt.partitions(&mut disk) {
+                            match partition {
+                                Ok(partition) => {
+                                    efi_println!(system_table, "#   {:?}", partition);
+                                },
+                                Err(err) => {
+                                    efi_println!(system_table, "!   failed to read partition");
+                                    efi_println!(system_table, "!   {:?}", err);
+                                    num_errs += 1;
+                                },
+                            }
+                        }
+                    },
+                    Err(err) => {
+                        efi_println!(system_table, "!   failed to read GPT");
+                        efi_println!(system_table, "!   {:?}", err);
+                        num_errs += 1;
+                    },
+                }
+            },
+            Ok(mbr) => {
+                for partition in mbr.used_partitions() {
+                    efi_println!(system_table, "#   {:?}", partition);
+                }
+            },
+            Err(Status::NotFound) => { },
+            Err(err) => {
+                efi_println!(system_table, "!   failed to read MBR");
+                efi_println!(system_table, "!   {:?}", err);
+                num_errs += 1;
+            },
+        }
+    }
+
+    if num_errs > 0 {
+        Err(num_errs)
+    } else {
+        Ok(())
+    }
+}
+
+
+fn test_volumes(image_handle: Handle, system_table: &SystemTable) -> Result<(), usize> {
+
+    let mut num_errs = 0;
+    efi_println!(system_table, "test volumes");
+
+    efi_println!(system_table, "    test open boot volume");
+    match Volume::boot_volume(&system_table.boot_services, image_handle) {
+        Ok(volume) => {
+            efi_println!(system_table, "#   partition info: {:?}", volume.partition_info());
+            efi_println!(system_table, "#   is esp: {}", volume.is_esp());
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to open boot volume");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test enumerate ESPs");
+    match Volume::esps(&system_table.boot_services, image_handle) {
+        Ok(esps) => {
+            for volume in esps {
+                efi_println!(system_table, "#   esp on handle {:x}", volume.handle());
+            }
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to enumerate ESPs");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
     if num_errs > 0 {
         Err(num_errs)
     } else {
@@ -415,6 +1326,96 @@ fn test_files_2(root: &File, system_table: &SystemTable) -> Result<(), usize> {
         },
     }
 
+    let bs = &*system_table.boot_services;
+
+    efi_println!(system_table, "    test create directories");
+    if let Err(err) = fs::create_dir_all(root, "libefi-test/a/b", bs) {
+        efi_println!(system_table, "!   failed to create directories");
+        efi_println!(system_table, "!   {:?}", err);
+        num_errs += 1;
+    }
+
+    efi_println!(system_table, "    test write and read back file");
+    let res = fs::write_all(root, "libefi-test/a/b/hello.txt", b"hello, world", bs)
+        .and_then(|_| fs::read_to_string(root, "/libefi-test\\a/b/hello.txt", bs));
+    match res {
+        Ok(ref contents) if *contents == &"hello, world" => { },
+        Ok(contents) => {
+            efi_println!(system_table, "!   read back wrong contents: {}", contents);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to write and read back file");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test copy and rename file");
+    let res = fs::copy(root, "libefi-test/a/b/hello.txt", "libefi-test/copy.txt", bs)
+        .and_then(|_| fs::rename(root, "libefi-test/copy.txt", "libefi-test/a/moved.txt", bs))
+        .and_then(|_| fs::metadata(root, "libefi-test/a/moved.txt", bs).map(|info| info.file_size));
+    match res {
+        Ok(12) => { },
+        Ok(size) => {
+            efi_println!(system_table, "!   copied file has wrong size: {}", size);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to copy and rename file");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test set file attributes");
+    let res = fs::open(root, "libefi-test/a/moved.txt", FileMode::READ | FileMode::WRITE,
+                       FileAttributes::empty(), bs)
+        .and_then(|file| {
+            file.set_attributes(FileAttributes::ARCHIVE, bs)?;
+            file.get_info::<FileInfo>(bs).map(|info| info.attribute)
+        });
+    match res {
+        Ok(attributes) if attributes.contains(FileAttributes::ARCHIVE) => { },
+        Ok(attributes) => {
+            efi_println!(system_table, "!   attributes were not changed: {:?}", attributes);
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to set file attributes");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test get volume label");
+    let res = root.get_info::<FileSystemVolumeLabel>(bs)
+        .and_then(|label| label.volume_label(bs));
+    match res {
+        Ok(label) => efi_println!(system_table, "#   volume label: {}", label),
+        Err(err) => {
+            efi_println!(system_table, "!   failed to get volume label");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
+    efi_println!(system_table, "    test remove directory tree");
+    let res = fs::remove_dir_all(root, "libefi-test", bs)
+        .and_then(|_| fs::exists(root, "libefi-test", bs));
+    match res {
+        Ok(false) => { },
+        Ok(true) => {
+            efi_println!(system_table, "!   directory still exists after removal");
+            num_errs += 1;
+        },
+        Err(err) => {
+            efi_println!(system_table, "!   failed to remove directory tree");
+            efi_println!(system_table, "!   {:?}", err);
+            num_errs += 1;
+        },
+    }
+
     if num_errs > 0 {
         Err(num_errs)
     } else {
@@ -423,6 +1424,57 @@ fn test_files_2(root: &File, system_table: &SystemTable) -> Result<(), usize> {
 }
 
 
+/// Protocol installed by the protocol tests
+#[repr(C)]
+struct TestProtocol {
+    value: u32,
+}
+
+impl Protocol for TestProtocol {
+
+    fn guid() -> &'static Guid { &TEST_PROTOCOL_GUID }
+}
+
+static TEST_PROTOCOL: TestProtocol = TestProtocol { value: 42 };
+
+static TEST_PROTOCOL_GUID: Guid = Guid {
+    data_1: 0x4c6f1e52,
+    data_2: 0x2b0d,
+    data_3: 0x4a8e,
+    data_4: [
+        0x9d,
+        0x31,
+        0x6e,
+        0x57,
+        0x0a,
+        0xc2,
+        0x8b,
+        0x14,
+    ],
+};
+
+
+/// Number of times the closures in the event tests have been called
+static NOTIFICATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Event group used by the event tests
+static TEST_EVENT_GROUP_GUID: Guid = Guid {
+    data_1: 0x9a0e5c37,
+    data_2: 0x61f4,
+    data_3: 0x4d2b,
+    data_4: [
+        0xb8,
+        0x13,
+        0x27,
+        0x5e,
+        0xc0,
+        0x94,
+        0x6a,
+        0xd1,
+    ],
+};
+
+
 extern "win64" fn empty_callback(_: &Event, _: &()) { }
 
 extern "win64" fn echo_callback(_: &Event, _: &&str) {}
@@ -437,6 +1489,10 @@ pub extern fn efi_main(image_handle: Handle, system_table: EfiRt<SystemTable>) -
         SYSTEM_TABLE = &*system_table;
     }
 
+    if let Err(num_errs) = test_console(&system_table) {
+        total_errs += num_errs;
+    }
+
     if let Err(num_errs) = test_events(&system_table) {
         total_errs += num_errs;
     }
@@ -453,6 +1509,26 @@ pub extern fn efi_main(image_handle: Handle, system_table: EfiRt<SystemTable>) -
         total_errs += num_errs;
     }
 
+    if let Err(num_errs) = test_graphics(image_handle, &system_table) {
+        total_errs += num_errs;
+    }
+
+    if let Err(num_errs) = test_images(&system_table) {
+        total_errs += num_errs;
+    }
+
+    if let Err(num_errs) = test_io(&system_table) {
+        total_errs += num_errs;
+    }
+
+    if let Err(num_errs) = test_block_io(image_handle, &system_table) {
+        total_errs += num_errs;
+    }
+
+    if let Err(num_errs) = test_volumes(image_handle, &system_table) {
+        total_errs += num_errs;
+    }
+
     if let Err(num_errs) = test_files(image_handle, &system_table) {
         total_errs += num_errs;
     }
@@ -0,0 +1,66 @@
+use types::{
+    Handle,
+    Status,
+};
+
+use super::BootServices;
+
+
+impl BootServices {
+
+    /// Connects drivers to a controller
+    ///
+    /// If `driver_image_handles` is given, those drivers are tried first, in order. If `recursive`
+    /// is true, drivers are also connected to any child controllers which get created.
+    pub fn connect_controller(
+        &self,
+        controller_handle: Handle,
+        driver_image_handles: Option<&[Handle]>,
+        remaining_device_path: Option<*const ()>,
+        recursive: bool
+    ) -> Result<(), Status> {
+
+        let remaining_device_path = remaining_device_path.unwrap_or(0 as *const ());
+
+        // The firmware expects a null-terminated list of handles
+        let handles = match driver_image_handles {
+            Some(handles) => {
+                let mut list = self.allocate_slice::<Handle>(handles.len() + 1)?;
+                list[..handles.len()].copy_from_slice(handles);
+                list[handles.len()] = 0;
+                Some(list)
+            },
+            None => None,
+        };
+        let handles_ptr = handles.as_ref().map_or(0 as *const Handle, |list| list.as_ptr());
+
+        (self._connect_controller)(
+            controller_handle,
+            handles_ptr,
+            remaining_device_path,
+            recursive.into()
+        )
+            .as_result()
+            .map(|_| ())
+    }
+
+    /// Disconnects drivers from a controller
+    ///
+    /// If `driver_image_handle` is `None`, all drivers are disconnected. If `child_handle` is
+    /// given, only that child controller is destroyed.
+    pub fn disconnect_controller(
+        &self,
+        controller_handle: Handle,
+        driver_image_handle: Option<Handle>,
+        child_handle: Option<Handle>
+    ) -> Result<(), Status> {
+
+        (self._disconnect_controller)(
+            controller_handle,
+            driver_image_handle.unwrap_or(0),
+            child_handle.unwrap_or(0)
+        )
+            .as_result()
+            .map(|_| ())
+    }
+}
@@ -1,10 +1,20 @@
-use super::BootServices;
+use super::{
+    BootServices,
+    Guid,
+};
 
 use types::{
+    MemoryType,
     Status,
 };
 
-use core::mem;
+use core::{
+    cmp,
+    mem,
+    ops,
+    ptr,
+    time::Duration,
+};
 
 
 /// UEFI Event
@@ -14,8 +24,83 @@ pub struct Event(());
 unsafe impl Sync for Event { }
 
 
+/// An event which is closed when dropped
+///
+/// Unlike the `&Event` returned by `BootServices::create_event`, the notification function of an
+/// owned event may be any `'static` closure. The closure is moved into pool memory, and is freed
+/// once the event has been closed and can no longer be notified. If closing fails, the closure is
+/// leaked rather than freed, since the firmware may still call it.
+pub struct OwnedEvent<'a> {
+    boot_services: &'a BootServices,
+    event: &'a Event,
+    callback: *mut u8,
+    drop_callback: unsafe fn(*mut u8),
+}
+
+impl<'a> OwnedEvent<'a> {
+
+    /// Closes the event, reporting any error
+    ///
+    /// Dropping the event also closes it, but ignores errors.
+    pub fn close(self) -> Result<(), Status> {
+
+        let res = self.close_and_free();
+        mem::forget(self);
+        res
+    }
+
+    /// Closes the event, then drops and frees the closure if closing succeeded
+    ///
+    /// This must only be called once.
+    fn close_and_free(&self) -> Result<(), Status> {
+
+        self.boot_services.close_event(self.event)?;
+
+        // Closing an event guarantees that its notification function won't be called again
+        unsafe { (self.drop_callback)(self.callback) };
+        self.boot_services.free_pool(self.callback)
+    }
+}
+
+impl<'a> ops::Deref for OwnedEvent<'a> {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+
+        self.event
+    }
+}
+
+impl<'a> ops::Drop for OwnedEvent<'a> {
+
+    fn drop(&mut self) {
+
+        let _ = self.close_and_free();
+    }
+}
+
+
+/// Notification function which calls a closure stored in pool memory
+extern "win64" fn notify_closure<F>(event: &Event, context: *const ())
+where F: FnMut(&Event) {
+
+    // The firmware never calls an event's notification function reentrantly, so there is only
+    // ever one mutable reference to the closure
+    let callback = unsafe { &mut *(context as *mut F) };
+    callback(event);
+}
+
+
+/// Drops a closure stored in pool memory
+pub(crate) unsafe fn drop_closure<F>(callback: *mut u8) {
+
+    ptr::drop_in_place(callback as *mut F);
+}
+
+
 bitflags! {
     /// Specifies an Event's mode and attributes
+    #[repr(transparent)]
     pub struct EventType: u32 {
         const TIMER = 0x8000_0000;
         const RUNTIME = 0x4000_0000;
@@ -28,6 +113,7 @@ bitflags! {
 
 
 /// Defines the type of a timer
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
 pub enum TimerDelay {
     Cancel,
@@ -36,8 +122,114 @@ pub enum TimerDelay {
 }
 
 
+/// Converts a duration to the 100ns units used by timer events
+pub(crate) fn to_timer_units(duration: Duration) -> u64 {
+
+    duration.as_secs()
+        .saturating_mul(10_000_000)
+        .saturating_add((duration.subsec_nanos() / 100) as u64)
+}
+
+
+/// Alignment guaranteed for allocations from pool memory
+pub(crate) const POOL_ALIGNMENT: usize = 8;
+
+
+/// Signaled when `exit_boot_services` is called
+///
+/// To clean up at that point, pass this group to `create_owned_event`. Keep the event alive until
+/// `exit_boot_services` succeeds, then `mem::forget` it, since it can no longer be closed.
+pub static EVENT_GROUP_EXIT_BOOT_SERVICES: Guid = Guid {
+    data_1: 0x27abf055,
+    data_2: 0xb1b8,
+    data_3: 0x4c26,
+    data_4: [
+        0x80,
+        0x48,
+        0x74,
+        0x8f,
+        0x37,
+        0xba,
+        0xa2,
+        0xdf,
+    ],
+};
+
+
+/// Signaled when the operating system calls `set_virtual_address_map`
+pub static EVENT_GROUP_VIRTUAL_ADDRESS_CHANGE: Guid = Guid {
+    data_1: 0x13fa7698,
+    data_2: 0xc831,
+    data_3: 0x49c7,
+    data_4: [
+        0x87,
+        0xea,
+        0x8f,
+        0x43,
+        0xfc,
+        0xc2,
+        0x51,
+        0x96,
+    ],
+};
+
+
+/// Signaled when the memory map changes
+pub static EVENT_GROUP_MEMORY_MAP_CHANGE: Guid = Guid {
+    data_1: 0x78bee926,
+    data_2: 0x692f,
+    data_3: 0x48fd,
+    data_4: [
+        0x9e,
+        0xdb,
+        0x01,
+        0x42,
+        0x2e,
+        0xf0,
+        0xd7,
+        0xab,
+    ],
+};
+
+
+/// Signaled just before the boot manager starts a boot option
+pub static EVENT_GROUP_READY_TO_BOOT: Guid = Guid {
+    data_1: 0x7ce88fb3,
+    data_2: 0x4bd7,
+    data_3: 0x4679,
+    data_4: [
+        0x87,
+        0xa8,
+        0xa8,
+        0xd8,
+        0xde,
+        0xe5,
+        0x0d,
+        0x2b,
+    ],
+};
+
+
+/// Signaled when `reset_system` is called
+pub static EVENT_GROUP_RESET_SYSTEM: Guid = Guid {
+    data_1: 0x62da6a56,
+    data_2: 0x13fb,
+    data_3: 0x485a,
+    data_4: [
+        0xa8,
+        0xda,
+        0xa3,
+        0xdd,
+        0x79,
+        0x12,
+        0xcb,
+        0x6b,
+    ],
+};
+
+
 /// Task priority level
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(usize)]
 pub enum TPL {
     Application = 4,
@@ -50,17 +242,25 @@ pub enum TPL {
 impl BootServices {
 
     /// Creates an event
-    pub fn create_event<T>(&self,
-                           event_type: EventType,
-                           notify_tpl: TPL,
-                           notify_function: extern "win64" fn(&Event, &T),
-                           notify_context: &T)
-        -> Result<&Event, Status> where T: ?Sized {
+    ///
+    /// Prefer `create_owned_event`, which is safe.
+    ///
+    /// # Safety
+    ///
+    /// The event must be closed with `close_event` before `notify_context` goes away, since the
+    /// firmware may call `notify_function` with it at any time until then. The returned reference
+    /// must not be used after the event is closed.
+    pub unsafe fn create_event<'a, T>(&'a self,
+                               event_type: EventType,
+                               notify_tpl: TPL,
+                               notify_function: extern "win64" fn(&Event, &T),
+                               notify_context: &'a T)
+        -> Result<&'a Event, Status> where T: ?Sized {
 
         // It's safe to cast notify_function to a different signature as long as the UEFI system
         // upholds its side of the spec and passes notify_context unmodified
         let notify_function: extern "win64" fn(&Event, *const ()) =
-            unsafe { mem::transmute(notify_function) };
+            mem::transmute(notify_function);
         let notify_context = notify_context as *const T as *const ();
 
         let mut event = &Event(());
@@ -69,6 +269,110 @@ impl BootServices {
             .map(|_| event)
     }
 
+    /// Creates an event which belongs to an event group
+    ///
+    /// Signaling any event in a group signals all of them, and the firmware signals some groups,
+    /// such as `EVENT_GROUP_EXIT_BOOT_SERVICES`, itself. `event_type` must not include
+    /// `SIGNAL_EXIT_BOOT_SERVICES` or `SIGNAL_VIRTUAL_ADDRESS_CHANGE`, since those are expressed
+    /// by the group instead. If `event_group` is `None`, this is the same as `create_event`.
+    ///
+    /// Prefer `create_owned_event`, which is safe and also accepts an event group. Notification
+    /// functions for `EVENT_GROUP_EXIT_BOOT_SERVICES` in particular are easily left registered
+    /// after their context goes away.
+    ///
+    /// # Safety
+    ///
+    /// The same requirements apply as for `create_event`.
+    pub unsafe fn create_event_ex<'a, T>(&'a self,
+                                  event_type: EventType,
+                                  notify_tpl: TPL,
+                                  notify_function: extern "win64" fn(&Event, &T),
+                                  notify_context: &'a T,
+                                  event_group: Option<&Guid>)
+        -> Result<&'a Event, Status> where T: ?Sized {
+
+        // See create_event
+        let notify_function: extern "win64" fn(&Event, *const ()) =
+            mem::transmute(notify_function);
+        let notify_context = notify_context as *const T as *const ();
+        let event_group: *const Guid = event_group
+            .map_or(0 as _, |g| g as _);
+
+        let mut event = &Event(());
+        (self._create_event_ex)(
+            event_type,
+            notify_tpl,
+            notify_function,
+            notify_context,
+            event_group,
+            &mut event
+        )
+            .as_result()
+            .map(|_| event)
+    }
+
+    /// Creates an event which is closed when dropped
+    ///
+    /// If `event_type` includes `NOTIFY_WAIT` or `NOTIFY_SIGNAL`, `callback` is called each time
+    /// the event is notified, at `notify_tpl`. Otherwise it is never called, and `|_| { }` may be
+    /// passed. If `event_group` is given, the event joins that group as with `create_event_ex`.
+    ///
+    /// The closure must be `'static` because leaking the returned event with `mem::forget` leaves
+    /// the firmware able to call it indefinitely. State shared with the closure should be kept in
+    /// statics.
+    ///
+    /// Fails with `Status::Unsupported` if the closure requires a greater alignment than pool
+    /// memory provides.
+    pub fn create_owned_event<'a, F>(&'a self,
+                                     event_type: EventType,
+                                     notify_tpl: TPL,
+                                     event_group: Option<&Guid>,
+                                     callback: F)
+        -> Result<OwnedEvent<'a>, Status> where F: FnMut(&Event) + 'static {
+
+        if mem::align_of::<F>() > POOL_ALIGNMENT {
+            return Err(Status::Unsupported);
+        }
+
+        // Move the closure into pool memory so that it has a stable address
+        let size = cmp::max(mem::size_of::<F>(), 1);
+        let context = self.allocate_pool(MemoryType::LoaderData, size)?;
+        unsafe { ptr::write(context as *mut F, callback) };
+
+        let notify_function: extern "win64" fn(&Event, *const ()) = notify_closure::<F>;
+        let event_group: *const Guid = event_group
+            .map_or(0 as _, |g| g as _);
+
+        let mut event = &Event(());
+        let res = (self._create_event_ex)(
+            event_type,
+            notify_tpl,
+            notify_function,
+            context as *const (),
+            event_group,
+            &mut event
+        )
+            .as_result();
+
+        match res {
+            Ok(_) => Ok(OwnedEvent {
+                boot_services: self,
+                event: event,
+                callback: context,
+                drop_callback: drop_closure::<F>,
+            }),
+            Err(err) => {
+                unsafe {
+                    drop_closure::<F>(context);
+                }
+
+                // The creation error is more useful than any error from freeing the closure
+                let _ = self.free_pool(context);
+                Err(err)
+            },
+        }
+    }
+
     /// Closes the given event
     pub fn close_event(&self, event: &Event) -> Result<(), Status> {
 
@@ -112,18 +416,20 @@ impl BootServices {
     }
 
     /// Raises a task’s priority level and returns its previous level.
-    pub fn raise_tpl(&self, new_tpl: TPL) -> Result<(), Status> {
+    ///
+    /// `new_tpl` must not be lower than the current level. The previous level is returned as a raw
+    /// value, since firmware may run at levels other than those named by `TPL`. Prefer `TplGuard`,
+    /// which checks the new level and restores the previous level automatically.
+    pub fn raise_tpl(&self, new_tpl: TPL) -> usize {
 
         (self._raise_tpl)(new_tpl)
-            .as_result()
-            .map(|_| ())
     }
 
     /// Restores a task’s priority level to its previous value.
-    pub fn restore_tpl(&self, old_tpl: TPL) -> Result<(), Status> {
+    ///
+    /// `old_tpl` must be the value returned by the matching call to `raise_tpl`.
+    pub fn restore_tpl(&self, old_tpl: usize) {
 
         (self._restore_tpl)(old_tpl)
-            .as_result()
-            .map(|_| ())
     }
 }
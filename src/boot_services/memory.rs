@@ -112,9 +112,12 @@ impl BootServices {
     }
 
     /// Allocates a slice from pool memory
+    ///
+    /// Fails with `Status::InvalidParameter` if the size of the slice in bytes would overflow.
     pub fn allocate_slice<'a, T>(&'a self, count: usize) -> Result<Pool<'a, [T]>, Status> {
 
-        let ptr = self.allocate_pool(MemoryType::LoaderData, count * mem::size_of::<T>())?;
+        let size = count.checked_mul(mem::size_of::<T>()).ok_or(Status::InvalidParameter)?;
+        let ptr = self.allocate_pool(MemoryType::LoaderData, size)?;
         unsafe {
             Ok(Pool::new_unchecked(
                 slice::from_raw_parts_mut(ptr as *mut T, count),
@@ -1,11 +1,16 @@
 //! EFI services available in a pre-boot environment
 
 
+mod driver;
 mod events;
 mod image;
 mod memory;
 mod pool_ptr;
 mod protocols;
+#[cfg(test)]
+pub(crate) mod testing;
+mod timer;
+mod tpl;
 
 
 use core::{
@@ -19,6 +24,7 @@ use core::{
     sync::atomic::AtomicPtr,
 };
 use types::{
+    Bool,
     Char16,
     EfiBs,
     Handle,
@@ -34,6 +40,8 @@ pub use self::image::*;
 pub use self::memory::*;
 pub use self::pool_ptr::*;
 pub use self::protocols::*;
+pub use self::timer::*;
+pub use self::tpl::*;
 
 
 /// Contains pointers to all of the boot services
@@ -42,8 +50,8 @@ pub struct BootServices {
     pub hdr: TableHeader,
 
     // Task Priority Services
-    pub _raise_tpl: extern "win64" fn(new_tpl: TPL) -> Status,
-    pub _restore_tpl: extern "win64" fn(old_tpl: TPL) -> Status,
+    pub _raise_tpl: extern "win64" fn(new_tpl: TPL) -> usize,
+    pub _restore_tpl: extern "win64" fn(old_tpl: usize),
 
     // Memory Services
     pub _allocate_pages: extern "win64" fn(
@@ -95,12 +103,30 @@ pub struct BootServices {
     pub _check_event: extern "win64" fn(event: &Event) -> Status,
 
     // Protocol Handler Services
-    pub _install_protocol_interface: extern "win64" fn(),
-    pub _reinstall_protocol_interface: extern "win64" fn(),
-    pub _uninstall_protocol_interface: extern "win64" fn(),
+    pub _install_protocol_interface: extern "win64" fn(
+        handle: &mut Handle,
+        protocol: &Guid,
+        interface_type: InterfaceType,
+        interface: *const ()
+    ) -> Status,
+    pub _reinstall_protocol_interface: extern "win64" fn(
+        handle: Handle,
+        protocol: &Guid,
+        old_interface: *const (),
+        new_interface: *const ()
+    ) -> Status,
+    pub _uninstall_protocol_interface: extern "win64" fn(
+        handle: Handle,
+        protocol: &Guid,
+        interface: *const ()
+    ) -> Status,
     pub _handle_protocol: extern "win64" fn(),
     reserved: AtomicPtr<()>,
-    pub _register_protocol_notify: extern "win64" fn(),
+    pub _register_protocol_notify: extern "win64" fn(
+        protocol: &Guid,
+        event: &Event,
+        registration: &mut *const ()
+    ) -> Status,
     pub _locate_handle: extern "win64" fn(
         search_type: SearchType,
         protocol: *const Guid,
@@ -120,12 +146,21 @@ pub struct BootServices {
 
     // Miscellaneous Services
     pub _get_next_monotonic_count: extern "win64" fn(),
-    pub _stall: extern "win64" fn(),
+    pub _stall: extern "win64" fn(microseconds: usize) -> Status,
     pub _set_watchdog_timer: extern "win64" fn(),
 
     // Driver Support Services
-    pub _connect_controller: extern "win64" fn(),
-    pub _disconnect_controller: extern "win64" fn(),
+    pub _connect_controller: extern "win64" fn(
+        controller_handle: Handle,
+        driver_image_handle: *const Handle,
+        remaining_device_path: *const (),
+        recursive: Bool
+    ) -> Status,
+    pub _disconnect_controller: extern "win64" fn(
+        controller_handle: Handle,
+        driver_image_handle: Handle,
+        child_handle: Handle
+    ) -> Status,
 
     // Open and Close Protocol Services
     pub _open_protocol: extern "win64" fn(
@@ -142,14 +177,39 @@ pub struct BootServices {
         agent_handle: Handle,
         controller_handle: Handle
     ) -> Status,
-    pub _open_protocol_information: extern "win64" fn(),
+    pub _open_protocol_information: extern "win64" fn(
+        handle: Handle,
+        protocol: &Guid,
+        entry_buffer: &mut *mut OpenProtocolInformationEntry,
+        entry_count: &mut usize
+    ) -> Status,
 
     // Library Services
-    pub _protocols_per_handle: extern "win64" fn(),
-    pub _locate_handle_buffer: extern "win64" fn(),
-    pub _locate_protocol: extern "win64" fn(),
-    pub _install_multiple_protocol_interfaces: extern "win64" fn(),
-    pub _uninstall_multiple_protocol_interfaces: extern "win64" fn(),
+    pub _protocols_per_handle: extern "win64" fn(
+        handle: Handle,
+        protocol_buffer: &mut *mut &Guid,
+        protocol_buffer_count: &mut usize
+    ) -> Status,
+    pub _locate_handle_buffer: extern "win64" fn(
+        search_type: SearchType,
+        protocol: *const Guid,
+        search_key: *const (),
+        no_handles: &mut usize,
+        buffer: &mut *mut Handle
+    ) -> Status,
+    pub _locate_protocol: extern "win64" fn(
+        protocol: &Guid,
+        registration: *const (),
+        interface: &mut EfiBs<()>
+    ) -> Status,
+    pub _install_multiple_protocol_interfaces: extern "win64" fn(
+        handle: &mut Handle,
+        ...
+    ) -> Status,
+    pub _uninstall_multiple_protocol_interfaces: extern "win64" fn(
+        handle: Handle,
+        ...
+    ) -> Status,
 
     // 32-bit CRC Services
     pub _calculate_crc32: extern "win64" fn(),
@@ -157,7 +217,14 @@ pub struct BootServices {
     // Miscellaneous Services
     pub _copy_mem: extern "win64" fn(),
     pub _set_mem: extern "win64" fn(buffer: *mut u8, size: usize, value: u8),
-    pub _create_event_ex: extern "win64" fn(),
+    pub _create_event_ex: extern "win64" fn(
+        event_type: EventType,
+        notify_tpl: TPL,
+        notify_function: extern "win64" fn(event: &Event, context: *const ()),
+        notify_context: *const (),
+        event_group: *const Guid,
+        event: &mut &Event
+    ) -> Status,
 }
 
 impl fmt::Debug for BootServices {
@@ -8,6 +8,7 @@ use core::{
         Pointer,
     },
     iter::Iterator,
+    mem,
     ops::{
         Deref,
         DerefMut,
@@ -45,6 +46,14 @@ impl<'a, T: ?Sized> Pool<'a, T> {
             boot_services: boot_services,
         }
     }
+
+    /// Consumes the `Pool` without freeing it, returning the underlying pointer
+    pub(crate) fn into_raw(self) -> *mut T {
+
+        let ptr = self.ptr.as_ptr();
+        mem::forget(self);
+        ptr
+    }
 }
 
 impl<'a, T: ?Sized + Debug> Debug for Pool<'a, T> {
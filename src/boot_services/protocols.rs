@@ -1,5 +1,8 @@
 use core::{
+    marker::PhantomData,
     mem,
+    ops,
+    ptr,
     slice,
 };
 use types::{
@@ -10,6 +13,7 @@ use types::{
 };
 use super::{
     BootServices,
+    Event,
     Pool,
 };
 
@@ -38,6 +42,17 @@ bitflags! {
 }
 
 
+/// Describes one agent which has a protocol open
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct OpenProtocolInformationEntry {
+    pub agent_handle: Handle,
+    pub controller_handle: Handle,
+    pub attributes: OpenProtocolAttributes,
+    pub open_count: u32,
+}
+
+
 /// Common functionality implemented by all protocols
 pub trait Protocol {
 
@@ -46,6 +61,156 @@ pub trait Protocol {
 }
 
 
+/// Marks a `ScopedProtocol` which may be shared with other agents
+#[derive(Debug)]
+pub enum Shared { }
+
+/// Marks a `ScopedProtocol` which was opened with `OpenProtocolAttributes::EXCLUSIVE`
+#[derive(Debug)]
+pub enum Exclusive { }
+
+
+/// A protocol interface which is closed when dropped
+///
+/// Interfaces opened exclusively may be mutated, since no other agent can be using them at the
+/// same time.
+#[derive(Debug)]
+pub struct ScopedProtocol<'bs, T, M = Shared>
+where T: Protocol {
+    boot_services: &'bs BootServices,
+    interface: *mut T,
+    handle: Handle,
+    agent_handle: Handle,
+    controller_handle: Handle,
+    _mode: PhantomData<M>,
+}
+
+impl<'bs, T, M> ScopedProtocol<'bs, T, M>
+where T: Protocol {
+
+    fn new(
+        boot_services: &'bs BootServices,
+        interface: EfiBs<T>,
+        handle: Handle,
+        agent_handle: Handle,
+        controller_handle: Handle
+    ) -> ScopedProtocol<'bs, T, M> {
+
+        ScopedProtocol {
+            boot_services: boot_services,
+            interface: interface.into_raw(),
+            handle: handle,
+            agent_handle: agent_handle,
+            controller_handle: controller_handle,
+            _mode: PhantomData,
+        }
+    }
+
+    /// Returns the handle which the interface was opened on
+    pub fn handle(&self) -> Handle {
+
+        self.handle
+    }
+}
+
+impl<'bs, T, M> ops::Deref for ScopedProtocol<'bs, T, M>
+where T: Protocol {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+
+        unsafe { &*self.interface }
+    }
+}
+
+impl<'bs, T> ops::DerefMut for ScopedProtocol<'bs, T, Exclusive>
+where T: Protocol {
+
+    fn deref_mut(&mut self) -> &mut T {
+
+        unsafe { &mut *self.interface }
+    }
+}
+
+impl<'bs, T, M> ops::Drop for ScopedProtocol<'bs, T, M>
+where T: Protocol {
+
+    fn drop(&mut self) {
+
+        // There is nothing useful to be done if closing fails
+        let _ = (self.boot_services._close_protocol)(
+            self.handle,
+            T::guid(),
+            self.agent_handle,
+            self.controller_handle
+        );
+    }
+}
+
+
+/// Kind of interface being installed on a handle
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum InterfaceType {
+    Native,
+}
+
+
+/// Maximum number of interfaces which can be passed to
+/// `BootServices::install_multiple_protocol_interfaces` in a single call
+pub const MAX_MULTIPLE_INTERFACES: usize = 8;
+
+
+/// A protocol interface paired with the Guid that identifies it
+///
+/// Used to install or uninstall several interfaces at once.
+#[derive(Clone, Copy, Debug)]
+pub struct ProtocolInterface {
+    guid: &'static Guid,
+    interface: *const (),
+}
+
+impl ProtocolInterface {
+
+    /// Pairs an interface with the Guid of its protocol
+    pub fn new<T>(interface: &'static T) -> ProtocolInterface
+    where T: Protocol {
+
+        ProtocolInterface {
+            guid: T::guid(),
+            interface: interface as *const T as *const (),
+        }
+    }
+
+    /// Pairs an arbitrary interface with a Guid
+    ///
+    /// # Safety
+    ///
+    /// `interface` must point to a valid instance of the protocol identified by `guid` (or be null,
+    /// for protocols which have no interface), and must remain valid for as long as it is
+    /// installed.
+    pub unsafe fn from_raw(guid: &'static Guid, interface: *const ()) -> ProtocolInterface {
+
+        ProtocolInterface {
+            guid: guid,
+            interface: interface,
+        }
+    }
+
+    /// Returns the Guid of the interface's protocol
+    pub fn guid(&self) -> &'static Guid {
+
+        self.guid
+    }
+
+    /// Returns a pointer to the interface
+    pub fn interface(&self) -> *const () {
+
+        self.interface
+    }
+}
+
+
 /// Specifies criteria used to search for available Handles
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
@@ -56,8 +221,68 @@ pub enum SearchType {
 }
 
 
+/// Registration for notifications when interfaces of protocol `T` are installed
+///
+/// Returned by `BootServices::register_protocol_notify`. The registration lasts until its event is
+/// closed.
+#[derive(Debug)]
+pub struct ProtocolRegistration<'a, T> {
+    key: *const (),
+    event: &'a Event,
+    _protocol: PhantomData<T>,
+}
+
+impl<'a, T> ProtocolRegistration<'a, T>
+where T: Protocol {
+
+    /// Returns the key to pass to `BootServices::locate_handle` with `SearchType::ByRegisterNotify`
+    pub fn key(&self) -> *const () {
+
+        self.key
+    }
+
+    /// Returns the event which is signaled when an interface is installed
+    pub fn event(&self) -> &'a Event {
+
+        self.event
+    }
+
+    /// Returns the next handle which an interface has been installed on since the last call, or
+    /// `None` if there are no more
+    pub fn next_handle(&self, boot_services: &BootServices) -> Result<Option<Handle>, Status> {
+
+        match boot_services.locate_handle(SearchType::ByRegisterNotify, None, Some(self.key)) {
+            Ok(handles) => Ok(handles.first().cloned()),
+            Err(Status::NotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+
 impl BootServices {
 
+    /// Signals an event whenever an interface of protocol `T` is installed or reinstalled
+    ///
+    /// Each handle which the interface was installed on can then be retrieved using the returned
+    /// registration. Interfaces which are already installed are not reported.
+    pub fn register_protocol_notify<'a, T>(
+        &self,
+        event: &'a Event
+    ) -> Result<ProtocolRegistration<'a, T>, Status>
+    where T: Protocol {
+
+        let mut key = 0 as *const ();
+        (self._register_protocol_notify)(T::guid(), event, &mut key)
+            .as_result()?;
+
+        Ok(ProtocolRegistration {
+            key: key,
+            event: event,
+            _protocol: PhantomData,
+        })
+    }
+
     /// Returns a slice of handles that support the specified protocols
     pub fn locate_handle<'a>(
         &'a self,
@@ -95,6 +320,175 @@ impl BootServices {
         }
     }
 
+    /// Returns a slice of handles that support the specified protocols, in a buffer allocated by
+    /// the firmware
+    ///
+    /// Unlike `locate_handle`, this requires only a single call into the firmware.
+    pub fn locate_handle_buffer<'a>(
+        &'a self,
+        search_type: SearchType,
+        protocol: Option<&Guid>,
+        search_key: Option<*const ()>
+    ) -> Result<Pool<'a, [Handle]>, Status> {
+
+        let protocol: *const Guid = protocol
+            .map_or(0 as _, |g| g as _);
+        let search_key = search_key
+            .unwrap_or(0 as *const ());
+        let mut num_handles = 0;
+        let mut buf = 0 as *mut Handle;
+        (self._locate_handle_buffer)(search_type, protocol, search_key, &mut num_handles, &mut buf)
+            .as_result()?;
+
+        unsafe {
+            Ok(Pool::new_unchecked(
+                slice::from_raw_parts_mut(buf, num_handles),
+                self
+            ))
+        }
+    }
+
+    /// Returns the first interface of the specified protocol found on any handle
+    pub fn locate_protocol<T>(&self) -> Result<EfiBs<T>, Status>
+    where T: Protocol {
+
+        let mut interface = unsafe { EfiBs::new() };
+        (self._locate_protocol)(T::guid(), 0 as *const (), &mut interface)
+            .as_result()?;
+
+        if interface.is_null() {
+            Err(Status::NotFound)
+        } else {
+            Ok(unsafe { mem::transmute(interface) })
+        }
+    }
+
+    /// Returns the Guids of all protocols installed on a handle
+    pub fn protocols_per_handle<'a>(
+        &'a self,
+        handle: Handle
+    ) -> Result<Pool<'a, [&'a Guid]>, Status> {
+
+        let mut buf = 0 as *mut &Guid;
+        let mut count = 0;
+        (self._protocols_per_handle)(handle, &mut buf, &mut count)
+            .as_result()?;
+
+        unsafe {
+            Ok(Pool::new_unchecked(
+                slice::from_raw_parts_mut(buf, count),
+                self
+            ))
+        }
+    }
+
+    /// Installs a protocol interface, returning the handle it was installed on
+    ///
+    /// If `handle` is `None`, a new handle is created. The interface is borrowed for `'static`
+    /// because other images may use it at any time until it is uninstalled.
+    pub fn install_protocol_interface<T>(
+        &self,
+        handle: Option<Handle>,
+        interface: &'static T
+    ) -> Result<Handle, Status>
+    where T: Protocol {
+
+        let mut handle = handle.unwrap_or(0);
+        (self._install_protocol_interface)(
+            &mut handle,
+            T::guid(),
+            InterfaceType::Native,
+            interface as *const T as *const ()
+        )
+            .as_result()?;
+
+        Ok(handle)
+    }
+
+    /// Replaces an installed protocol interface with a new one
+    ///
+    /// Agents which opened `old_interface` by driver are disconnected and then asked to connect
+    /// again, so that they can begin using `new_interface`.
+    pub fn reinstall_protocol_interface<T>(
+        &self,
+        handle: Handle,
+        old_interface: &T,
+        new_interface: &'static T
+    ) -> Result<(), Status>
+    where T: Protocol {
+
+        (self._reinstall_protocol_interface)(
+            handle,
+            T::guid(),
+            old_interface as *const T as *const (),
+            new_interface as *const T as *const ()
+        )
+            .as_result()
+            .map(|_| ())
+    }
+
+    /// Removes a protocol interface from a handle
+    ///
+    /// Fails with `Status::AccessDenied` if the interface is still in use by another agent. Once
+    /// this succeeds, the interface may be freed. The handle itself is freed along with its last
+    /// interface.
+    pub fn uninstall_protocol_interface<T>(
+        &self,
+        handle: Handle,
+        interface: &T
+    ) -> Result<(), Status>
+    where T: Protocol {
+
+        (self._uninstall_protocol_interface)(handle, T::guid(), interface as *const T as *const ())
+            .as_result()
+            .map(|_| ())
+    }
+
+    /// Installs several protocol interfaces at once, returning the handle they were installed on
+    ///
+    /// If `handle` is `None`, a new handle is created. Either all of the interfaces are installed
+    /// or none of them are. Fails with `Status::InvalidParameter` if more than
+    /// `MAX_MULTIPLE_INTERFACES` interfaces are given.
+    pub fn install_multiple_protocol_interfaces(
+        &self,
+        handle: Option<Handle>,
+        interfaces: &[ProtocolInterface]
+    ) -> Result<Handle, Status> {
+
+        let args = multiple_interface_args(interfaces)?;
+        let mut handle = handle.unwrap_or(0);
+        (self._install_multiple_protocol_interfaces)(
+            &mut handle,
+            args[0], args[1], args[2], args[3], args[4], args[5], args[6], args[7],
+            args[8], args[9], args[10], args[11], args[12], args[13], args[14], args[15],
+            args[16]
+        )
+            .as_result()?;
+
+        Ok(handle)
+    }
+
+    /// Removes several protocol interfaces from a handle at once
+    ///
+    /// Either all of the interfaces are removed or none of them are. Fails with
+    /// `Status::InvalidParameter` if more than `MAX_MULTIPLE_INTERFACES` interfaces are given.
+    pub fn uninstall_multiple_protocol_interfaces(
+        &self,
+        handle: Handle,
+        interfaces: &[ProtocolInterface]
+    ) -> Result<(), Status> {
+
+        let args = multiple_interface_args(interfaces)?;
+        (self._uninstall_multiple_protocol_interfaces)(
+            handle,
+            args[0], args[1], args[2], args[3], args[4], args[5], args[6], args[7],
+            args[8], args[9], args[10], args[11], args[12], args[13], args[14], args[15],
+            args[16]
+        )
+            .as_result()
+            .map(|_| ())
+    }
+
     /// Opens the specified protocol on behalf of the calling agent
     pub fn open_protocol<T>(
         &self,
@@ -123,6 +517,88 @@ impl BootServices {
         }
     }
 
+    /// Opens the specified protocol, closing it again when the returned guard is dropped
+    ///
+    /// Fails with `Status::InvalidParameter` if `attributes` contains `EXCLUSIVE`; use
+    /// `open_protocol_exclusive` instead.
+    pub fn open_protocol_scoped<'bs, T>(
+        &'bs self,
+        handle: Handle,
+        agent_handle: Handle,
+        controller_handle: Handle,
+        attributes: OpenProtocolAttributes
+    ) -> Result<ScopedProtocol<'bs, T>, Status>
+    where T: Protocol {
+
+        if attributes.contains(OpenProtocolAttributes::EXCLUSIVE) {
+            return Err(Status::InvalidParameter);
+        }
+
+        let interface = self.open_protocol(handle, agent_handle, controller_handle, attributes)?;
+        Ok(ScopedProtocol::new(self, interface, handle, agent_handle, controller_handle))
+    }
+
+    /// Opens the specified protocol for exclusive use, closing it again when the returned guard is
+    /// dropped
+    ///
+    /// Drivers should set `by_driver`, so that the open is recorded against `controller_handle`.
+    /// Fails with `Status::AccessDenied` if another agent already has the protocol open
+    /// exclusively or by driver.
+    pub fn open_protocol_exclusive<'bs, T>(
+        &'bs self,
+        handle: Handle,
+        agent_handle: Handle,
+        controller_handle: Handle,
+        by_driver: bool
+    ) -> Result<ScopedProtocol<'bs, T, Exclusive>, Status>
+    where T: Protocol {
+
+        let attributes = if by_driver {
+            OpenProtocolAttributes::BY_DRIVER | OpenProtocolAttributes::EXCLUSIVE
+        } else {
+            OpenProtocolAttributes::EXCLUSIVE
+        };
+        let interface = self.open_protocol(handle, agent_handle, controller_handle, attributes)?;
+        Ok(ScopedProtocol::new(self, interface, handle, agent_handle, controller_handle))
+    }
+
+    /// Gets the specified protocol from a handle, in the manner of the firmware's `HandleProtocol`
+    ///
+    /// This opens the protocol with `OpenProtocolAttributes::GET_PROTOCOL`, which never conflicts
+    /// with other agents.
+    pub fn handle_protocol<'bs, T>(
+        &'bs self,
+        handle: Handle,
+        agent_handle: Handle
+    ) -> Result<ScopedProtocol<'bs, T>, Status>
+    where T: Protocol {
+
+        self.open_protocol_scoped(handle, agent_handle, 0, OpenProtocolAttributes::GET_PROTOCOL)
+    }
+
+    /// Returns a list of the agents which currently have the specified protocol open on a handle
+    ///
+    /// This is useful for finding out which agent holds a protocol when opening it fails with
+    /// `Status::AccessDenied`.
+    pub fn open_protocol_information<'a, T>(
+        &'a self,
+        handle: Handle
+    ) -> Result<Pool<'a, [OpenProtocolInformationEntry]>, Status>
+    where T: Protocol {
+
+        let mut buf = 0 as *mut OpenProtocolInformationEntry;
+        let mut count = 0;
+        (self._open_protocol_information)(handle, T::guid(), &mut buf, &mut count)
+            .as_result()?;
+
+        unsafe {
+            Ok(Pool::new_unchecked(
+                slice::from_raw_parts_mut(buf, count),
+                self
+            ))
+        }
+    }
+
     /// Closes the specified protocol that was previously opened on the specified `handle`
     pub fn close_protocol<T>(
         &self,
@@ -138,3 +614,26 @@ impl BootServices {
             .map(|_| ())
     }
 }
+
+
+/// Flattens interfaces into the null-terminated list of variable arguments expected by
+/// `_install_multiple_protocol_interfaces` and `_uninstall_multiple_protocol_interfaces`
+///
+/// Unused arguments after the terminator are also null, so that every call can pass the same
+/// number of arguments.
+fn multiple_interface_args(
+    interfaces: &[ProtocolInterface]
+) -> Result<[*const (); MAX_MULTIPLE_INTERFACES * 2 + 1], Status> {
+
+    if interfaces.len() > MAX_MULTIPLE_INTERFACES {
+        return Err(Status::InvalidParameter);
+    }
+
+    let mut args = [ptr::null(); MAX_MULTIPLE_INTERFACES * 2 + 1];
+    for (i, interface) in interfaces.iter().enumerate() {
+        args[i * 2] = interface.guid as *const Guid as *const ();
+        args[i * 2 + 1] = interface.interface;
+    }
+
+    Ok(args)
+}
@@ -0,0 +1,80 @@
+//! Host implementation of the boot services needed by unit tests
+
+
+use core::mem::{
+    self,
+    MaybeUninit,
+};
+use std::{
+    alloc::{
+        self,
+        Layout,
+    },
+    boxed::Box,
+};
+use types::{
+    MemoryType,
+    Status,
+};
+use super::BootServices;
+
+
+/// Size of the header which records the size of each allocation
+const HEADER_SIZE: usize = 16;
+
+
+/// Returns boot services which support pool allocation and nothing else
+///
+/// Calling any other service aborts the test.
+pub(crate) fn boot_services() -> &'static BootServices {
+
+    let mut boot_services = MaybeUninit::<BootServices>::uninit();
+    unsafe {
+        // Every field of BootServices is pointer-sized, so each one can be given a valid function
+        // pointer before the ones which are actually implemented are filled in
+        let words = boot_services.as_mut_ptr() as *mut usize;
+        for i in 0..mem::size_of::<BootServices>() / mem::size_of::<usize>() {
+            *words.add(i) = unimplemented_service as extern "win64" fn() as usize;
+        }
+
+        let mut boot_services = boot_services.assume_init();
+        boot_services._allocate_pool = allocate_pool;
+        boot_services._free_pool = free_pool;
+        Box::leak(Box::new(boot_services))
+    }
+}
+
+
+extern "win64" fn unimplemented_service() {
+
+    panic!("boot service is not implemented on the host");
+}
+
+
+extern "win64" fn allocate_pool(_: MemoryType, size: usize, buffer: &mut *mut u8) -> Status {
+
+    unsafe {
+        let layout = Layout::from_size_align_unchecked(size + HEADER_SIZE, HEADER_SIZE);
+        let ptr = alloc::alloc(layout);
+        if ptr.is_null() {
+            return Status::OutOfResources;
+        }
+
+        *(ptr as *mut usize) = size;
+        *buffer = ptr.add(HEADER_SIZE);
+    }
+
+    Status::Success
+}
+
+
+extern "win64" fn free_pool(buffer: *mut u8) -> Status {
+
+    unsafe {
+        let ptr = buffer.sub(HEADER_SIZE);
+        let size = *(ptr as *mut usize);
+        alloc::dealloc(ptr, Layout::from_size_align_unchecked(size + HEADER_SIZE, HEADER_SIZE));
+    }
+
+    Status::Success
+}
@@ -0,0 +1,166 @@
+use core::{
+    cmp,
+    ops,
+    time::Duration,
+};
+use types::Status;
+
+use super::{
+    BootServices,
+    Event,
+    EventType,
+    OwnedEvent,
+    TimerDelay,
+    TPL,
+    to_timer_units,
+};
+
+
+/// Number of events which `wait_for_event_timeout` can wait on without allocating
+const MAX_STACK_EVENTS: usize = 8;
+
+
+/// A timer event which is cancelled and closed when dropped
+pub struct Timer<'a> {
+    boot_services: &'a BootServices,
+    event: OwnedEvent<'a>,
+}
+
+impl<'a> Timer<'a> {
+
+    /// Creates a timer which is signaled once, after `duration` has elapsed
+    pub fn one_shot(
+        boot_services: &'a BootServices,
+        duration: Duration
+    ) -> Result<Timer<'a>, Status> {
+
+        Timer::new(boot_services, TimerDelay::Relative, duration)
+    }
+
+    /// Creates a timer which is signaled repeatedly, each time `period` has elapsed
+    pub fn periodic(
+        boot_services: &'a BootServices,
+        period: Duration
+    ) -> Result<Timer<'a>, Status> {
+
+        Timer::new(boot_services, TimerDelay::Periodic, period)
+    }
+
+    fn new(
+        boot_services: &'a BootServices,
+        timer_type: TimerDelay,
+        duration: Duration
+    ) -> Result<Timer<'a>, Status> {
+
+        let event = boot_services.create_owned_event(
+            EventType::TIMER,
+            TPL::Callback,
+            None,
+            |_| { }
+        )?;
+        boot_services.set_timer(&event, timer_type, to_timer_units(duration))?;
+
+        Ok(Timer {
+            boot_services: boot_services,
+            event: event,
+        })
+    }
+
+    /// Waits until the timer is signaled
+    ///
+    /// For a periodic timer, this waits for the next period to elapse unless a period has elapsed
+    /// since the last wait.
+    pub fn wait(&self) -> Result<(), Status> {
+
+        self.boot_services.wait_for_event(&[&self.event])
+            .map(|_| ())
+    }
+
+    /// Tells whether the timer has been signaled since it was last waited on or checked
+    pub fn check(&self) -> Result<bool, Status> {
+
+        match self.boot_services.check_event(&self.event) {
+            Ok(()) => Ok(true),
+            Err(Status::NotReady) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Stops the timer, so that it is not signaled again
+    pub fn cancel(&self) -> Result<(), Status> {
+
+        self.boot_services.set_timer(&self.event, TimerDelay::Cancel, 0)
+    }
+}
+
+impl<'a> ops::Deref for Timer<'a> {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+
+        &self.event
+    }
+}
+
+impl<'a> ops::Drop for Timer<'a> {
+
+    fn drop(&mut self) {
+
+        // The event is closed afterwards when it is dropped
+        let _ = self.cancel();
+    }
+}
+
+
+impl BootServices {
+
+    /// Busy-waits for at least the given duration
+    ///
+    /// Durations are rounded up to whole microseconds. Unlike waiting on a `Timer`, this does not
+    /// allow other events to be processed, so it should only be used for short delays.
+    pub fn stall(&self, duration: Duration) -> Result<(), Status> {
+
+        let mut microseconds = duration.as_secs()
+            .saturating_mul(1_000_000)
+            .saturating_add(((duration.subsec_nanos() + 999) / 1_000) as u64);
+
+        // Long durations are split up where usize is narrower than u64
+        while microseconds > 0 {
+            let chunk = cmp::min(microseconds, usize::max_value() as u64);
+            (self._stall)(chunk as usize)
+                .as_result()?;
+            microseconds -= chunk;
+        }
+
+        Ok(())
+    }
+
+    /// Waits until one of the given events is signaled or the timeout expires
+    ///
+    /// Returns the index of the signaled event, or `Status::Timeout` if the timeout expires first.
+    /// Pool memory is only allocated when waiting on more than a few events.
+    pub fn wait_for_event_timeout(
+        &self,
+        events: &[&Event],
+        timeout: Duration
+    ) -> Result<usize, Status> {
+
+        let timer = Timer::one_shot(self, timeout)?;
+        let count = events.len() + 1;
+        let index = if count <= MAX_STACK_EVENTS {
+            let mut all_events = [&*timer; MAX_STACK_EVENTS];
+            all_events[..events.len()].copy_from_slice(events);
+            self.wait_for_event(&all_events[..count])?
+        } else {
+            let mut all_events = self.allocate_slice::<&Event>(count)?;
+            all_events[..events.len()].copy_from_slice(events);
+            all_events[events.len()] = &timer;
+            self.wait_for_event(&all_events)?
+        };
+
+        match index {
+            index if index == events.len() => Err(Status::Timeout),
+            index => Ok(index),
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use types::Status;
+use super::{
+    BootServices,
+    TPL,
+};
+
+
+/// Raises the task priority level, restoring the previous level when dropped
+///
+/// While the level is raised, notification functions of events with a lower or equal `notify_tpl`
+/// are deferred. Guards must be dropped in the reverse order of their creation.
+#[derive(Debug)]
+pub struct TplGuard<'a> {
+    boot_services: &'a BootServices,
+    old_tpl: usize,
+}
+
+impl<'a> TplGuard<'a> {
+
+    /// Raises the task priority level to `new_tpl`
+    ///
+    /// Fails with `Status::InvalidParameter` if `new_tpl` is lower than the current level.
+    pub fn new(boot_services: &'a BootServices, new_tpl: TPL) -> Result<TplGuard<'a>, Status> {
+
+        // Raising to the highest level is always allowed, and reveals the current level
+        let current_tpl = boot_services.raise_tpl(TPL::HightLevel);
+        boot_services.restore_tpl(current_tpl);
+        if current_tpl > new_tpl as usize {
+            return Err(Status::InvalidParameter);
+        }
+
+        Ok(TplGuard {
+            boot_services: boot_services,
+            old_tpl: boot_services.raise_tpl(new_tpl),
+        })
+    }
+
+    /// Returns the raw level which will be restored when the guard is dropped
+    pub fn old_tpl(&self) -> usize {
+
+        self.old_tpl
+    }
+}
+
+impl<'a> Drop for TplGuard<'a> {
+
+    fn drop(&mut self) {
+
+        self.boot_services.restore_tpl(self.old_tpl);
+    }
+}
+
+
+/// Runs a closure with all event notifications and timer interrupts blocked
+///
+/// This protects state which is shared with event notification functions. The closure should be
+/// short, and must not wait for events.
+pub fn critical_section<R, F>(boot_services: &BootServices, f: F) -> R
+where F: FnOnce() -> R {
+
+    // No level is higher, so this is always allowed
+    let _guard = TplGuard {
+        boot_services: boot_services,
+        old_tpl: boot_services.raise_tpl(TPL::HightLevel),
+    };
+    f()
+}
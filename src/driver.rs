@@ -0,0 +1,345 @@
+//! Writing UEFI drivers
+//!
+//! A driver implements the `Driver` trait and calls `install` from its entry point. This publishes
+//! `DriverBinding` (and `ComponentName2`, if the driver has a name) on the driver's image handle,
+//! after which the firmware calls back into the driver whenever a controller is connected.
+//!
+//! Driver images must be built with one of the driver target specifications, such as
+//! `x86_64-pc-uefi-bs-driver`, and should return from `efi_main` after installing.
+
+
+use core::{
+    mem,
+    ptr,
+    slice,
+};
+use {
+    boot_services::{
+        BootServices,
+        POOL_ALIGNMENT,
+        Protocol,
+        ProtocolInterface,
+    },
+    protocols::{
+        ComponentName2,
+        DriverBinding,
+    },
+    types::{
+        Char16,
+        Handle,
+        MemoryType,
+        Status,
+    },
+    SystemTable,
+};
+
+
+/// Languages supported by the names a driver provides
+const SUPPORTED_LANGUAGES: &[u8] = b"en\0";
+
+
+/// A driver which manages controllers
+///
+/// The firmware never calls these methods concurrently, but it may call them reentrantly, such
+/// as when `start` connects child controllers. Any state which changes should therefore be kept
+/// behind interior mutability.
+///
+/// In each method, `agent_handle` is the handle which `DriverBinding` is installed on. It should
+/// be passed as the agent when opening protocols on `controller_handle`.
+pub trait Driver {
+
+    /// Tells whether the driver can manage a controller
+    ///
+    /// This is called often, so it should do no more than open (and close again) the protocols
+    /// which the driver needs.
+    fn supported(
+        &self,
+        boot_services: &BootServices,
+        agent_handle: Handle,
+        controller_handle: Handle,
+        remaining_device_path: *const ()
+    ) -> Result<(), Status>;
+
+    /// Starts managing a controller
+    fn start(
+        &self,
+        boot_services: &BootServices,
+        agent_handle: Handle,
+        controller_handle: Handle,
+        remaining_device_path: *const ()
+    ) -> Result<(), Status>;
+
+    /// Stops managing a controller
+    ///
+    /// If `children` is not empty, only those child controllers should be destroyed.
+    fn stop(
+        &self,
+        boot_services: &BootServices,
+        agent_handle: Handle,
+        controller_handle: Handle,
+        children: &[Handle]
+    ) -> Result<(), Status>;
+
+    /// Returns the English name of the driver, including a null terminator
+    ///
+    /// `ComponentName2` is only installed if this returns a name. A name without a null
+    /// terminator is never passed to the firmware; `Status::Unsupported` is returned instead.
+    fn driver_name(&self) -> Option<&'static [Char16]> {
+
+        None
+    }
+
+    /// Returns the English name of a controller managed by the driver, including a null
+    /// terminator
+    ///
+    /// If `child_handle` is given, the name of that child controller should be returned instead.
+    /// As with `driver_name`, the name must be null-terminated.
+    fn controller_name(
+        &self,
+        _controller_handle: Handle,
+        _child_handle: Option<Handle>
+    ) -> Option<&'static [Char16]> {
+
+        None
+    }
+}
+
+
+/// State shared by the protocols installed for a driver
+///
+/// The protocols are the leading fields, so that the firmware's pointers to them can be converted
+/// back into a pointer to the whole instance.
+#[repr(C)]
+struct DriverInstance<D> {
+    driver_binding: DriverBinding,
+    component_name: ComponentName2,
+    boot_services: *const BootServices,
+    driver: D,
+}
+
+impl<D: Driver> DriverInstance<D> {
+
+    fn from_driver_binding(this: &DriverBinding) -> &DriverInstance<D> {
+
+        unsafe { &*(this as *const DriverBinding as *const DriverInstance<D>) }
+    }
+
+    fn from_component_name(this: &ComponentName2) -> &DriverInstance<D> {
+
+        // The instance is repr(C), so component_name starts at the first multiple of its alignment
+        // after driver_binding. The size of DriverBinding is already such a multiple, since it is
+        // rounded up to the alignment of its pointer fields.
+        unsafe {
+            let ptr = (this as *const ComponentName2 as *const u8)
+                .offset(-(mem::size_of::<DriverBinding>() as isize));
+            &*(ptr as *const DriverInstance<D>)
+        }
+    }
+
+    fn boot_services(&self) -> &BootServices {
+
+        unsafe { &*self.boot_services }
+    }
+}
+
+
+/// Installs a driver on its own image handle
+///
+/// This should be called from `efi_main`. `version` is used by the firmware to prefer newer
+/// drivers over older ones. The driver is moved into pool memory and remains installed until the
+/// image is unloaded.
+///
+/// Fails with `Status::Unsupported` if the driver requires a greater alignment than pool memory
+/// provides.
+pub fn install<D: Driver>(
+    image_handle: Handle,
+    system_table: &SystemTable,
+    driver: D,
+    version: u32
+) -> Result<(), Status> {
+
+    if mem::align_of::<DriverInstance<D>>() > POOL_ALIGNMENT {
+        return Err(Status::Unsupported);
+    }
+
+    let boot_services = &*system_table.boot_services;
+    let size = mem::size_of::<DriverInstance<D>>();
+    let instance = boot_services.allocate_pool(MemoryType::BootServicesData, size)?
+        as *mut DriverInstance<D>;
+
+    unsafe {
+        ptr::write(instance, DriverInstance {
+            driver_binding: DriverBinding {
+                _supported: supported::<D>,
+                _start: start::<D>,
+                _stop: stop::<D>,
+                version: version,
+                image_handle: image_handle,
+                driver_binding_handle: image_handle,
+            },
+            component_name: ComponentName2 {
+                _get_driver_name: get_driver_name::<D>,
+                _get_controller_name: get_controller_name::<D>,
+                supported_languages: SUPPORTED_LANGUAGES.as_ptr(),
+            },
+            boot_services: boot_services,
+            driver: driver,
+        });
+
+        let driver_binding = ProtocolInterface::from_raw(
+            DriverBinding::guid(),
+            &(*instance).driver_binding as *const DriverBinding as *const ()
+        );
+        let component_name = ProtocolInterface::from_raw(
+            ComponentName2::guid(),
+            &(*instance).component_name as *const ComponentName2 as *const ()
+        );
+        let interfaces = [driver_binding, component_name];
+        let interfaces = match (*instance).driver.driver_name() {
+            Some(_) => &interfaces[..],
+            None => &interfaces[..1],
+        };
+
+        let res = boot_services.install_multiple_protocol_interfaces(
+            Some(image_handle),
+            interfaces
+        );
+        if res.is_err() {
+            // Report the installation error rather than any error from freeing
+            ptr::drop_in_place(instance);
+            let _ = boot_services.free_pool(instance as *mut u8);
+        }
+
+        res.map(|_| ())
+    }
+}
+
+
+extern "win64" fn supported<D: Driver>(
+    this: &DriverBinding,
+    controller_handle: Handle,
+    remaining_device_path: *const ()
+) -> Status {
+
+    let instance = DriverInstance::<D>::from_driver_binding(this);
+    let res = instance.driver.supported(
+        instance.boot_services(),
+        this.driver_binding_handle,
+        controller_handle,
+        remaining_device_path
+    );
+
+    to_status(res)
+}
+
+extern "win64" fn start<D: Driver>(
+    this: &DriverBinding,
+    controller_handle: Handle,
+    remaining_device_path: *const ()
+) -> Status {
+
+    let instance = DriverInstance::<D>::from_driver_binding(this);
+    let res = instance.driver.start(
+        instance.boot_services(),
+        this.driver_binding_handle,
+        controller_handle,
+        remaining_device_path
+    );
+
+    to_status(res)
+}
+
+extern "win64" fn stop<D: Driver>(
+    this: &DriverBinding,
+    controller_handle: Handle,
+    number_of_children: usize,
+    child_handle_buffer: *const Handle
+) -> Status {
+
+    let instance = DriverInstance::<D>::from_driver_binding(this);
+    let children = if number_of_children == 0 || child_handle_buffer.is_null() {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(child_handle_buffer, number_of_children) }
+    };
+    let res = instance.driver.stop(
+        instance.boot_services(),
+        this.driver_binding_handle,
+        controller_handle,
+        children
+    );
+
+    to_status(res)
+}
+
+extern "win64" fn get_driver_name<D: Driver>(
+    this: &ComponentName2,
+    language: *const u8,
+    driver_name: &mut *const Char16
+) -> Status {
+
+    if !is_supported_language(language) {
+        return Status::Unsupported;
+    }
+
+    let instance = DriverInstance::<D>::from_component_name(this);
+    match instance.driver.driver_name() {
+        Some(name) if name.last() == Some(&0) => {
+            *driver_name = name.as_ptr();
+            Status::Success
+        },
+        _ => Status::Unsupported,
+    }
+}
+
+extern "win64" fn get_controller_name<D: Driver>(
+    this: &ComponentName2,
+    controller_handle: Handle,
+    child_handle: Handle,
+    language: *const u8,
+    controller_name: &mut *const Char16
+) -> Status {
+
+    if !is_supported_language(language) {
+        return Status::Unsupported;
+    }
+
+    let instance = DriverInstance::<D>::from_component_name(this);
+    let child_handle = if child_handle == 0 { None } else { Some(child_handle) };
+    match instance.driver.controller_name(controller_handle, child_handle) {
+        Some(name) if name.last() == Some(&0) => {
+            *controller_name = name.as_ptr();
+            Status::Success
+        },
+        _ => Status::Unsupported,
+    }
+}
+
+
+/// Tells whether a null-terminated language code is one of `SUPPORTED_LANGUAGES`
+fn is_supported_language(language: *const u8) -> bool {
+
+    if language.is_null() {
+        return false;
+    }
+
+    // Only the primary language subtag is compared, so that "en-US" matches "en"
+    let mut len = 0;
+    unsafe {
+        while *language.offset(len) != 0 && *language.offset(len) != b'-' {
+            len += 1;
+        }
+        slice::from_raw_parts(language, len as usize) == &SUPPORTED_LANGUAGES[..2]
+    }
+}
+
+
+/// Converts the result of a driver method into the status expected by the firmware
+fn to_status(res: Result<(), Status>) -> Status {
+
+    match res {
+        Ok(()) => Status::Success,
+        Err(err) => err,
+    }
+}
+
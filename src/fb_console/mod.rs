@@ -0,0 +1,324 @@
+//! Text console rendered directly onto a framebuffer
+//!
+//! Unlike `SimpleTextOutput`, an `FbConsole` only ever touches framebuffer memory, so it keeps
+//! working after exiting boot services.
+
+
+mod font;
+
+use core::fmt;
+use {
+    protocols::{
+        Attribute,
+        BltPixel,
+        Color,
+        FrameBuffer,
+    },
+    PrintTarget,
+};
+
+
+/// Bitmap font used to render text
+///
+/// Each glyph is stored as a series of rows, and each row occupies `(width + 7) / 8` bytes with the
+/// most significant bit as the leftmost pixel.
+#[derive(Clone, Copy, Debug)]
+pub struct Font<'a> {
+    width: usize,
+    height: usize,
+    first_char: u32,
+    glyph_count: usize,
+    glyphs: &'a [u8],
+}
+
+impl Font<'static> {
+
+    /// Returns the built-in 8x8 font, which covers printable ASCII characters
+    pub fn builtin() -> Font<'static> {
+
+        Font {
+            width: 8,
+            height: 8,
+            first_char: font::FIRST_CHAR,
+            glyph_count: font::GLYPH_COUNT,
+            glyphs: &font::GLYPHS,
+        }
+    }
+}
+
+impl<'a> Font<'a> {
+
+    /// Loads a font in PC Screen Font version 1 format
+    ///
+    /// Glyphs are looked up by their index in the font, so the Unicode table (if present) is
+    /// ignored. Returns `None` if `data` is not a valid PSF1 font.
+    pub fn from_psf1(data: &'a [u8]) -> Option<Font<'a>> {
+
+        const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+        const PSF1_MODE512: u8 = 0x01;
+
+        if data.len() < 4 || data[0..2] != PSF1_MAGIC {
+            return None;
+        }
+
+        let height = data[3] as usize;
+        let glyph_count = if data[2] & PSF1_MODE512 != 0 { 512 } else { 256 };
+        let glyphs = data.get(4..4 + glyph_count * height)?;
+
+        Some(Font {
+            width: 8,
+            height: height,
+            first_char: 0,
+            glyph_count: glyph_count,
+            glyphs: glyphs,
+        })
+    }
+
+    /// Returns the width of each glyph in pixels
+    pub fn width(&self) -> usize {
+
+        self.width
+    }
+
+    /// Returns the height of each glyph in pixels
+    pub fn height(&self) -> usize {
+
+        self.height
+    }
+
+    /// Returns the bitmap for a character, if the font contains it
+    pub fn glyph(&self, c: char) -> Option<&'a [u8]> {
+
+        let index = (c as u32).checked_sub(self.first_char)? as usize;
+        if index >= self.glyph_count {
+            return None;
+        }
+
+        let glyph_size = self.height * ((self.width + 7) / 8);
+        self.glyphs.get(index * glyph_size..(index + 1) * glyph_size)
+    }
+}
+
+
+/// Text console which draws characters onto a framebuffer
+///
+/// Text wraps at the right edge of the screen, and the console scrolls once the bottom row is
+/// full. Both `\r` and `\n` are honored, so output written with `efi_println!` appears as
+/// expected.
+pub struct FbConsole<'a> {
+    frame_buffer: FrameBuffer<'a>,
+    font: Font<'a>,
+    scale: usize,
+    columns: usize,
+    rows: usize,
+    column: usize,
+    row: usize,
+    foreground: BltPixel,
+    background: BltPixel,
+}
+
+impl<'a> FbConsole<'a> {
+
+    /// Creates a console covering the entire framebuffer
+    ///
+    /// Each pixel of the font is drawn as a `scale` by `scale` square, which keeps text legible on
+    /// high-resolution screens. Text is initially light gray on black.
+    pub fn new(frame_buffer: FrameBuffer<'a>, font: Font<'a>, scale: usize) -> FbConsole<'a> {
+
+        let scale = if scale == 0 { 1 } else { scale };
+        let columns = frame_buffer.width() / (font.width() * scale);
+        let rows = frame_buffer.height() / (font.height() * scale);
+
+        FbConsole {
+            frame_buffer: frame_buffer,
+            font: font,
+            scale: scale,
+            columns: columns,
+            rows: rows,
+            column: 0,
+            row: 0,
+            foreground: color_to_pixel(Color::LightGray),
+            background: color_to_pixel(Color::Black),
+        }
+    }
+
+    /// Returns the number of text columns
+    pub fn columns(&self) -> usize {
+
+        self.columns
+    }
+
+    /// Returns the number of text rows
+    pub fn rows(&self) -> usize {
+
+        self.rows
+    }
+
+    /// Returns the current cursor position as a (column, row) pair
+    pub fn cursor_position(&self) -> (usize, usize) {
+
+        (self.column, self.row)
+    }
+
+    /// Sets the current cursor position
+    ///
+    /// Positions beyond the edge of the screen are clamped.
+    pub fn set_cursor_position(&mut self, column: usize, row: usize) {
+
+        self.column = column.min(self.columns.saturating_sub(1));
+        self.row = row.min(self.rows.saturating_sub(1));
+    }
+
+    /// Sets the foreground and background color of text that is written
+    pub fn set_colors(&mut self, foreground: BltPixel, background: BltPixel) {
+
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    /// Sets the colors of text that is written, using the same palette as `SimpleTextOutput`
+    pub fn set_attribute(&mut self, attribute: Attribute) {
+
+        self.set_colors(color_to_pixel(attribute.foreground), color_to_pixel(attribute.background));
+    }
+
+    /// Fills the screen with the background color and moves the cursor to the top left
+    pub fn clear(&mut self) {
+
+        let (width, height) = (self.frame_buffer.width(), self.frame_buffer.height());
+        let background = self.background;
+        self.frame_buffer.fill_rect(0, 0, width, height, background);
+        self.column = 0;
+        self.row = 0;
+    }
+
+    /// Writes a single character at the cursor and advances the cursor
+    pub fn write_char(&mut self, c: char) {
+
+        match c {
+            '\r' => self.column = 0,
+            '\n' => self.new_line(),
+            '\t' => {
+                let spaces = 8 - self.column % 8;
+                for _ in 0..spaces {
+                    self.write_char(' ');
+                }
+            },
+            '\x08' => self.column = self.column.saturating_sub(1),
+            _ => {
+                if self.column >= self.columns {
+                    self.new_line();
+                }
+                self.draw_glyph(c);
+                self.column += 1;
+            },
+        }
+    }
+
+    /// Moves the cursor to the start of the next row, scrolling if necessary
+    fn new_line(&mut self) {
+
+        self.column = 0;
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            self.scroll();
+        }
+    }
+
+    /// Scrolls the text up by one row and clears the bottom row
+    fn scroll(&mut self) {
+
+        let row_height = self.font.height() * self.scale;
+        let width = self.frame_buffer.width();
+        let background = self.background;
+        if self.rows > 0 {
+            self.frame_buffer.copy_rows(row_height, 0, (self.rows - 1) * row_height);
+            self.frame_buffer.fill_rect(
+                0,
+                (self.rows - 1) * row_height,
+                width,
+                row_height,
+                background
+            );
+        }
+    }
+
+    /// Draws a character at the cursor
+    ///
+    /// Characters missing from the font are drawn as `?`.
+    fn draw_glyph(&mut self, c: char) {
+
+        let glyph = match self.font.glyph(c).or_else(|| self.font.glyph('?')) {
+            Some(glyph) => glyph,
+            None => return,
+        };
+
+        let (width, height, scale) = (self.font.width(), self.font.height(), self.scale);
+        let bytes_per_row = (width + 7) / 8;
+        let x = self.column * width * scale;
+        let y = self.row * height * scale;
+        for glyph_y in 0..height {
+            for glyph_x in 0..width {
+                let byte = glyph[glyph_y * bytes_per_row + glyph_x / 8];
+                let pixel = if byte & (0x80 >> (glyph_x % 8)) != 0 {
+                    self.foreground
+                } else {
+                    self.background
+                };
+                self.frame_buffer.fill_rect(
+                    x + glyph_x * scale,
+                    y + glyph_y * scale,
+                    scale,
+                    scale,
+                    pixel
+                );
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Write for FbConsole<'a> {
+
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+
+        for c in s.chars() {
+            self.write_char(c);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> PrintTarget for &'a mut FbConsole<'b> {
+
+    fn print_fmt(self, args: fmt::Arguments) -> fmt::Result {
+
+        fmt::Write::write_fmt(self, args)
+    }
+}
+
+
+/// Returns the pixel used to display a console color
+///
+/// This matches the palette used by typical VGA text consoles.
+fn color_to_pixel(color: Color) -> BltPixel {
+
+    match color {
+        Color::Black => BltPixel::new(0x00, 0x00, 0x00),
+        Color::Blue => BltPixel::new(0x00, 0x00, 0xaa),
+        Color::Green => BltPixel::new(0x00, 0xaa, 0x00),
+        Color::Cyan => BltPixel::new(0x00, 0xaa, 0xaa),
+        Color::Red => BltPixel::new(0xaa, 0x00, 0x00),
+        Color::Magenta => BltPixel::new(0xaa, 0x00, 0xaa),
+        Color::Brown => BltPixel::new(0xaa, 0x55, 0x00),
+        Color::LightGray => BltPixel::new(0xaa, 0xaa, 0xaa),
+        Color::DarkGray => BltPixel::new(0x55, 0x55, 0x55),
+        Color::LightBlue => BltPixel::new(0x55, 0x55, 0xff),
+        Color::LightGreen => BltPixel::new(0x55, 0xff, 0x55),
+        Color::LightCyan => BltPixel::new(0x55, 0xff, 0xff),
+        Color::LightRed => BltPixel::new(0xff, 0x55, 0x55),
+        Color::LightMagenta => BltPixel::new(0xff, 0x55, 0xff),
+        Color::Yellow => BltPixel::new(0xff, 0xff, 0x55),
+        Color::White => BltPixel::new(0xff, 0xff, 0xff),
+    }
+}
@@ -0,0 +1,379 @@
+//! Path-based access to files
+//!
+//! These functions operate on paths relative to the root directory of a volume, such as the one
+//! returned by `SimpleFileSystem::open_volume`. Paths are ordinary `&str`s using either `/` or `\`
+//! as the separator; leading, trailing and repeated separators are ignored.
+
+
+use core::str;
+use {
+    boot_services::{
+        BootServices,
+        Pool,
+        utf16_to_str,
+    },
+    protocols::{
+        File,
+        FileAttributes,
+        FileInfo,
+        FileMode,
+        OwnedFile,
+    },
+    types::{
+        Char16,
+        Status,
+    },
+};
+
+
+/// Size of the buffer used when copying files
+const COPY_BUFFER_SIZE: usize = 4096;
+
+/// Initial size of the buffer used when reading directory entries
+const ENTRY_BUFFER_SIZE: usize = 512;
+
+/// Path separator understood by the firmware
+const SEPARATOR: Char16 = '\\' as Char16;
+
+
+/// Opens a file
+pub fn open(
+    root: &File,
+    path: &str,
+    mode: FileMode,
+    attributes: FileAttributes,
+    boot_services: &BootServices
+) -> Result<OwnedFile, Status> {
+
+    let path = to_utf16_path(path, boot_services)?;
+    root.open(&path, mode, attributes)
+        .map(OwnedFile::new)
+}
+
+
+/// Tells whether a file or directory exists
+pub fn exists(root: &File, path: &str, boot_services: &BootServices) -> Result<bool, Status> {
+
+    match open(root, path, FileMode::READ, FileAttributes::empty(), boot_services) {
+        Ok(_) => Ok(true),
+        Err(Status::NotFound) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+
+/// Returns information about a file or directory
+pub fn metadata<'a>(
+    root: &File,
+    path: &str,
+    boot_services: &'a BootServices
+) -> Result<Pool<'a, FileInfo>, Status> {
+
+    open(root, path, FileMode::READ, FileAttributes::empty(), boot_services)?
+        .get_info::<FileInfo>(boot_services)
+}
+
+
+/// Creates a directory along with any missing parent directories
+///
+/// Fails with `Status::InvalidParameter` if any component of the path is an existing file.
+pub fn create_dir_all(root: &File, path: &str, boot_services: &BootServices) -> Result<(), Status> {
+
+    let mut path = to_utf16_path(path, boot_services)?;
+    let len = path.iter().position(|c| *c == 0).unwrap_or(path.len());
+
+    // Open each ancestor in turn by temporarily terminating the path at its separators, skipping
+    // the leading separator which refers to the root
+    for end in 2..len + 1 {
+        if end < len && path[end] != SEPARATOR {
+            continue;
+        }
+
+        let saved = path[end];
+        path[end] = 0;
+        let res = root.open(
+            &path,
+            FileMode::READ | FileMode::WRITE | FileMode::CREATE,
+            FileAttributes::DIRECTORY
+        );
+        path[end] = saved;
+
+        let dir = OwnedFile::new(res?);
+        if !dir.get_info::<FileInfo>(boot_services)?.is_directory() {
+            return Err(Status::InvalidParameter);
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Reads the entire contents of a file into pool memory
+pub fn read_to_end<'a>(
+    root: &File,
+    path: &str,
+    boot_services: &'a BootServices
+) -> Result<Pool<'a, [u8]>, Status> {
+
+    let file = open(root, path, FileMode::READ, FileAttributes::empty(), boot_services)?;
+    let size = file.get_info::<FileInfo>(boot_services)?.file_size as usize;
+    let mut data = boot_services.allocate_slice::<u8>(size)?;
+    let mut read = 0;
+    while read < size {
+        let len = file.read(&mut data[read..])?;
+        if len == 0 {
+            return Err(Status::EndOfFile);
+        }
+        read += len;
+    }
+
+    Ok(data)
+}
+
+
+/// Reads the entire contents of a file into pool memory as a string
+///
+/// Fails with `Status::InvalidParameter` if the file is not valid UTF-8.
+pub fn read_to_string<'a>(
+    root: &File,
+    path: &str,
+    boot_services: &'a BootServices
+) -> Result<Pool<'a, str>, Status> {
+
+    let data = read_to_end(root, path, boot_services)?;
+    if str::from_utf8(&data).is_err() {
+        return Err(Status::InvalidParameter);
+    }
+
+    unsafe { Ok(Pool::new_unchecked(data.into_raw() as *mut str, boot_services)) }
+}
+
+
+/// Writes data to a file, creating it if necessary and replacing any existing contents
+pub fn write_all(
+    root: &File,
+    path: &str,
+    data: &[u8],
+    boot_services: &BootServices
+) -> Result<(), Status> {
+
+    let file = create(root, path, boot_services)?;
+    write_to(&file, data)
+}
+
+
+/// Copies the contents of a file, returning the number of bytes copied
+///
+/// The destination is created if necessary and any existing contents are replaced. Fails with
+/// `Status::InvalidParameter` if the source is a directory.
+pub fn copy(
+    root: &File,
+    from: &str,
+    to: &str,
+    boot_services: &BootServices
+) -> Result<u64, Status> {
+
+    let source = open(root, from, FileMode::READ, FileAttributes::empty(), boot_services)?;
+    if source.get_info::<FileInfo>(boot_services)?.is_directory() {
+        return Err(Status::InvalidParameter);
+    }
+
+    let dest = create(root, to, boot_services)?;
+    let mut buf = [0; COPY_BUFFER_SIZE];
+    let mut copied = 0;
+    loop {
+        let len = source.read(&mut buf)?;
+        if len == 0 {
+            return Ok(copied);
+        }
+        write_to(&dest, &buf[..len])?;
+        copied += len as u64;
+    }
+}
+
+
+/// Renames or moves a file or directory within the same volume
+pub fn rename(
+    root: &File,
+    from: &str,
+    to: &str,
+    boot_services: &BootServices
+) -> Result<(), Status> {
+
+    let file = open(
+        root,
+        from,
+        FileMode::READ | FileMode::WRITE,
+        FileAttributes::empty(),
+        boot_services
+    )?;
+    // A leading separator makes the new name relative to the root of the volume
+    let name = to_utf16_path(to, boot_services)?;
+    let len = name.iter().position(|c| *c == 0).unwrap_or(name.len());
+    let name = utf16_to_str(&name[..len], boot_services)?;
+    let info = file.get_info::<FileInfo>(boot_services)?
+        .with_file_name(&name, boot_services)?;
+
+    file.set_info(&*info)
+}
+
+
+/// Deletes a file
+///
+/// Fails with `Status::InvalidParameter` if the path refers to a directory.
+pub fn remove_file(root: &File, path: &str, boot_services: &BootServices) -> Result<(), Status> {
+
+    let file = open(
+        root,
+        path,
+        FileMode::READ | FileMode::WRITE,
+        FileAttributes::empty(),
+        boot_services
+    )?;
+    if file.get_info::<FileInfo>(boot_services)?.is_directory() {
+        return Err(Status::InvalidParameter);
+    }
+
+    file.delete()
+}
+
+
+/// Deletes a directory along with everything it contains
+pub fn remove_dir_all(root: &File, path: &str, boot_services: &BootServices) -> Result<(), Status> {
+
+    let dir = open(
+        root,
+        path,
+        FileMode::READ | FileMode::WRITE,
+        FileAttributes::empty(),
+        boot_services
+    )?;
+    remove_contents(&dir, boot_services)?;
+    dir.delete()
+}
+
+
+/// Converts a path into the null-terminated form expected by `File::open`
+fn to_utf16_path<'a>(
+    path: &str,
+    boot_services: &'a BootServices
+) -> Result<Pool<'a, [Char16]>, Status> {
+
+    // Each component gains a leading separator, so this is always large enough
+    let mut buf = boot_services.allocate_slice::<Char16>(path.encode_utf16().count() + 2)?;
+    let mut len = 0;
+    for component in path.split(|c| c == '/' || c == '\\').filter(|c| !c.is_empty()) {
+        buf[len] = SEPARATOR;
+        len += 1;
+        for unit in component.encode_utf16() {
+            buf[len] = unit;
+            len += 1;
+        }
+    }
+
+    // An empty path refers to the root itself
+    if len == 0 {
+        buf[0] = SEPARATOR;
+        len = 1;
+    }
+    buf[len] = 0;
+
+    Ok(buf)
+}
+
+
+/// Opens a file for writing, creating it if necessary and discarding any existing contents
+fn create(root: &File, path: &str, boot_services: &BootServices) -> Result<OwnedFile, Status> {
+
+    let file = open(
+        root,
+        path,
+        FileMode::READ | FileMode::WRITE | FileMode::CREATE,
+        FileAttributes::empty(),
+        boot_services
+    )?;
+
+    let mut info = file.get_info::<FileInfo>(boot_services)?;
+    if info.is_directory() {
+        return Err(Status::InvalidParameter);
+    }
+    if info.file_size != 0 {
+        info.file_size = 0;
+        file.set_info(&*info)?;
+    }
+
+    Ok(file)
+}
+
+
+/// Writes all of `data` to a file
+fn write_to(file: &File, mut data: &[u8]) -> Result<(), Status> {
+
+    while !data.is_empty() {
+        let len = file.write(data)?;
+        if len == 0 {
+            return Err(Status::VolumeFull);
+        }
+        data = &data[len..];
+    }
+
+    Ok(())
+}
+
+
+/// Recursively deletes the contents of a directory
+fn remove_contents(dir: &File, boot_services: &BootServices) -> Result<(), Status> {
+
+    // Deleting entries while reading a directory may cause entries to be skipped, so restart
+    // from the beginning after each deletion
+    loop {
+        dir.set_position(0)?;
+        let entry = loop {
+            match read_entry(dir, boot_services)? {
+                Some(entry) => {
+                    let name = entry.file_name();
+                    if name != ['.' as Char16] && name != ['.' as Char16, '.' as Char16] {
+                        break entry;
+                    }
+                },
+                None => return Ok(()),
+            }
+        };
+
+        let name = entry.file_name();
+        let mut path = boot_services.allocate_slice::<Char16>(name.len() + 1)?;
+        path[..name.len()].copy_from_slice(name);
+        path[name.len()] = 0;
+        let child = OwnedFile::new(
+            dir.open(&path, FileMode::READ | FileMode::WRITE, FileAttributes::empty())?
+        );
+        if entry.is_directory() {
+            remove_contents(&child, boot_services)?;
+        }
+        child.delete()?;
+    }
+}
+
+
+/// Reads the next entry of a directory, returning `None` once all entries have been read
+fn read_entry<'a>(
+    dir: &File,
+    boot_services: &'a BootServices
+) -> Result<Option<Pool<'a, FileInfo>>, Status> {
+
+    let mut size = ENTRY_BUFFER_SIZE;
+    loop {
+        let mut buf = boot_services.allocate_slice::<u8>(size)?;
+        let mut len = size;
+        match (dir._read)(dir, &mut len, buf.as_mut_ptr()) {
+            Status::Success if len == 0 => return Ok(None),
+            Status::Success => {
+                let ptr = buf.into_raw() as *mut u8 as *mut FileInfo;
+                return Ok(Some(unsafe { Pool::new_unchecked(ptr, boot_services) }));
+            },
+            // The required size has been stored in len
+            Status::BufferTooSmall => size = len,
+            err => return Err(err),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! BMP decoding
+
+
+use boot_services::BootServices;
+use io::Read;
+use protocols::BltPixel;
+use types::Status;
+use super::Image;
+
+
+/// Size of the file header which precedes the DIB header
+const FILE_HEADER_SIZE: usize = 14;
+
+/// Compression types
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+
+/// Tells whether the data looks like a BMP file
+pub(super) fn is_bmp(data: &[u8]) -> bool {
+
+    data.starts_with(b"BM")
+}
+
+
+/// Decodes an uncompressed 24-bit or 32-bit BMP
+pub(super) fn decode<'a>(
+    data: &[u8],
+    boot_services: &'a BootServices
+) -> Result<Image<'a>, Status> {
+
+    let header = Header::parse(data).ok_or(Status::InvalidParameter)?;
+    let bytes_per_pixel = (header.bits_per_pixel / 8) as usize;
+    let row_size = header.width
+        .checked_mul(bytes_per_pixel)
+        .and_then(|size| size.checked_add(3))
+        .ok_or(Status::InvalidParameter)? & !3;
+    let end = row_size
+        .checked_mul(header.height)
+        .and_then(|size| size.checked_add(header.pixel_offset));
+    match end {
+        Some(end) if end <= data.len() => { },
+        _ => return Err(Status::InvalidParameter),
+    }
+
+    let mut image = Image::allocate(header.width, header.height, boot_services)?;
+    for y in 0..header.height {
+        // Rows are stored bottom to top unless the height is negative
+        let source_y = if header.top_down { y } else { header.height - 1 - y };
+        let row = &data[header.pixel_offset + source_y * row_size..];
+        for x in 0..header.width {
+            let mut pixel = &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel];
+            image.pixels[y * header.width + x] = match header.masks {
+                Some(masks) => {
+                    let value = pixel.read_u32_le().unwrap_or(0);
+                    BltPixel {
+                        red: extract_channel(value, masks[0]),
+                        green: extract_channel(value, masks[1]),
+                        blue: extract_channel(value, masks[2]),
+                        reserved: match masks[3] {
+                            0 => 0xff,
+                            mask => extract_channel(value, mask),
+                        },
+                    }
+                },
+                None => BltPixel {
+                    blue: pixel[0],
+                    green: pixel[1],
+                    red: pixel[2],
+                    reserved: 0xff,
+                },
+            };
+        }
+    }
+
+    Ok(image)
+}
+
+
+/// Relevant fields of the BMP file and DIB headers
+struct Header {
+    pixel_offset: usize,
+    width: usize,
+    height: usize,
+    top_down: bool,
+    bits_per_pixel: u16,
+    masks: Option<[u32; 4]>,
+}
+
+impl Header {
+
+    /// Parses and validates the headers, returning `None` if the BMP is malformed or unsupported
+    fn parse(data: &[u8]) -> Option<Header> {
+
+        let mut fields = data.get(10..)?;
+        let pixel_offset = fields.read_u32_le().ok()? as usize;
+        let dib_size = fields.read_u32_le().ok()? as usize;
+        if dib_size < 40 {
+            return None;
+        }
+
+        let width = fields.read_u32_le().ok()? as i32;
+        let height = fields.read_u32_le().ok()? as i32;
+        // Skip the number of color planes
+        fields.read_u16_le().ok()?;
+        let bits_per_pixel = fields.read_u16_le().ok()?;
+        let compression = fields.read_u32_le().ok()?;
+        if width < 0 || (bits_per_pixel != 24 && bits_per_pixel != 32) {
+            return None;
+        }
+
+        // Bit fields follow the 40-byte header, or are part of the larger V4 and V5 headers
+        let masks = match compression {
+            BI_RGB => None,
+            BI_BITFIELDS | BI_ALPHABITFIELDS if bits_per_pixel == 32 => {
+                let mut fields = data.get(FILE_HEADER_SIZE + 40..)?;
+                let red = fields.read_u32_le().ok()?;
+                let green = fields.read_u32_le().ok()?;
+                let blue = fields.read_u32_le().ok()?;
+                let alpha = if dib_size >= 56 || compression == BI_ALPHABITFIELDS {
+                    fields.read_u32_le().ok()?
+                } else {
+                    0
+                };
+                Some([red, green, blue, alpha])
+            },
+            _ => return None,
+        };
+
+        Some(Header {
+            pixel_offset: pixel_offset,
+            width: width as usize,
+            height: height.checked_abs()? as usize,
+            top_down: height < 0,
+            bits_per_pixel: bits_per_pixel,
+            masks: masks,
+        })
+    }
+}
+
+
+/// Scales the bits selected by `mask` into an 8-bit color channel
+fn extract_channel(value: u32, mask: u32) -> u8 {
+
+    if mask == 0 {
+        return 0;
+    }
+
+    let bits = mask.count_ones();
+    let value = (value & mask) >> mask.trailing_zeros();
+    if bits >= 8 {
+        (value >> (bits - 8)) as u8
+    } else {
+        // Scale so that the full range of the channel maps to 0x00..0xff
+        (value * 0xff / ((1 << bits) - 1)) as u8
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::vec::Vec;
+    use boot_services::testing;
+    use protocols::BltPixel;
+    use types::Status;
+    use super::*;
+
+    fn header(width: i32, height: i32, bits_per_pixel: u16, compression: u32) -> Vec<u8> {
+
+        let dib_size: u32 = if compression == BI_RGB { 40 } else { 56 };
+        let pixel_offset = FILE_HEADER_SIZE as u32 + dib_size;
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&pixel_offset.to_le_bytes());
+        data.extend_from_slice(&dib_size.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&bits_per_pixel.to_le_bytes());
+        data.extend_from_slice(&compression.to_le_bytes());
+        data.extend_from_slice(&[0; 20]);
+        if compression != BI_RGB {
+            for mask in &[0x00ff_0000u32, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] {
+                data.extend_from_slice(&mask.to_le_bytes());
+            }
+        }
+        data
+    }
+
+    fn rgb(red: u8, green: u8, blue: u8) -> BltPixel {
+
+        BltPixel { blue: blue, green: green, red: red, reserved: 0xff }
+    }
+
+    #[test]
+    fn decodes_bottom_up_24_bit() {
+
+        let mut data = header(2, 2, 24, BI_RGB);
+        // Bottom row first, each padded to four bytes
+        data.extend_from_slice(&[3, 2, 1, 6, 5, 4, 0, 0]);
+        data.extend_from_slice(&[9, 8, 7, 12, 11, 10, 0, 0]);
+
+        let image = decode(&data, testing::boot_services()).unwrap();
+        assert_eq!(image.width(), 2);
+        assert_eq!(image.height(), 2);
+        assert_eq!(image.pixels(), &[
+            rgb(7, 8, 9),
+            rgb(10, 11, 12),
+            rgb(1, 2, 3),
+            rgb(4, 5, 6),
+        ]);
+    }
+
+    #[test]
+    fn decodes_top_down_bit_fields() {
+
+        let mut data = header(1, -2, 32, BI_BITFIELDS);
+        data.extend_from_slice(&0x8001_0203u32.to_le_bytes());
+        data.extend_from_slice(&0xff04_0506u32.to_le_bytes());
+
+        let image = decode(&data, testing::boot_services()).unwrap();
+        assert_eq!(image.pixels(), &[
+            BltPixel { blue: 3, green: 2, red: 1, reserved: 0x80 },
+            BltPixel { blue: 6, green: 5, red: 4, reserved: 0xff },
+        ]);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+
+        let mut data = header(2, 2, 24, BI_RGB);
+        data.extend_from_slice(&[3, 2, 1, 6, 5, 4, 0, 0]);
+
+        let res = decode(&data, testing::boot_services());
+        assert_eq!(res.err(), Some(Status::InvalidParameter));
+
+        let res = decode(&data[..20], testing::boot_services());
+        assert_eq!(res.err(), Some(Status::InvalidParameter));
+    }
+
+    #[test]
+    fn rejects_overflowing_dimensions() {
+
+        let data = header(i32::max_value(), i32::min_value(), 32, BI_RGB);
+
+        let res = decode(&data, testing::boot_services());
+        assert_eq!(res.err(), Some(Status::InvalidParameter));
+    }
+}
@@ -0,0 +1,203 @@
+//! Decoding of images for display on a graphics device
+//!
+//! Images are decoded into pool memory as `BltPixel`s, so they can be drawn with the Blt
+//! operations of `GraphicsOutput` or written directly to a `FrameBuffer`. The following formats
+//! are supported:
+//!
+//! * uncompressed 24-bit and 32-bit BMP
+//! * QOI
+
+
+mod bmp;
+mod qoi;
+
+use core::cmp;
+use {
+    boot_services::{
+        BootServices,
+        Pool,
+    },
+    protocols::{
+        BltPixel,
+        File,
+        FileInfo,
+        FrameBuffer,
+        GraphicsOutput,
+    },
+    types::Status,
+};
+
+
+/// A decoded image
+///
+/// Pixels are stored in rows from top to bottom. The `reserved` field of each pixel holds its
+/// alpha value, where 0xff is fully opaque; Blt operations ignore it.
+pub struct Image<'a> {
+    width: usize,
+    height: usize,
+    pixels: Pool<'a, [BltPixel]>,
+}
+
+impl<'a> Image<'a> {
+
+    /// Decodes an image, detecting its format from its contents
+    ///
+    /// Fails with `Status::Unsupported` if the format is not recognized, or with
+    /// `Status::InvalidParameter` if the data is malformed.
+    pub fn decode(data: &[u8], boot_services: &'a BootServices) -> Result<Image<'a>, Status> {
+
+        if bmp::is_bmp(data) {
+            bmp::decode(data, boot_services)
+        } else if qoi::is_qoi(data) {
+            qoi::decode(data, boot_services)
+        } else {
+            Err(Status::Unsupported)
+        }
+    }
+
+    /// Reads an entire file and decodes it as an image
+    pub fn load(file: &File, boot_services: &'a BootServices) -> Result<Image<'a>, Status> {
+
+        let size = file.get_info::<FileInfo>(boot_services)?.file_size as usize;
+        let mut data = boot_services.allocate_slice::<u8>(size)?;
+        let mut read = 0;
+        while read < size {
+            let len = file.read(&mut data[read..])?;
+            if len == 0 {
+                return Err(Status::EndOfFile);
+            }
+            read += len;
+        }
+
+        Image::decode(&data, boot_services)
+    }
+
+    /// Allocates an image whose pixels are not yet initialized
+    fn allocate(
+        width: usize,
+        height: usize,
+        boot_services: &'a BootServices
+    ) -> Result<Image<'a>, Status> {
+
+        let len = width.checked_mul(height).ok_or(Status::InvalidParameter)?;
+        Ok(Image {
+            width: width,
+            height: height,
+            pixels: boot_services.allocate_slice(len)?,
+        })
+    }
+
+    /// Returns the width of the image in pixels
+    pub fn width(&self) -> usize {
+
+        self.width
+    }
+
+    /// Returns the height of the image in pixels
+    pub fn height(&self) -> usize {
+
+        self.height
+    }
+
+    /// Returns the pixels of the image, in rows from top to bottom
+    pub fn pixels(&self) -> &[BltPixel] {
+
+        &self.pixels
+    }
+
+    /// Returns the position at which the image is centered within an area of the given size
+    ///
+    /// If the image is larger than the area, the position is clamped to the top left.
+    pub fn centered(&self, width: usize, height: usize) -> (usize, usize) {
+
+        (width.saturating_sub(self.width) / 2, height.saturating_sub(self.height) / 2)
+    }
+
+    /// Returns a copy of this image resized to the given dimensions
+    ///
+    /// Nearest-neighbor sampling is used, which keeps logos crisp when scaled by whole numbers.
+    pub fn scale<'b>(
+        &self,
+        width: usize,
+        height: usize,
+        boot_services: &'b BootServices
+    ) -> Result<Image<'b>, Status> {
+
+        let mut scaled = Image::allocate(width, height, boot_services)?;
+        if self.width == 0 || self.height == 0 {
+            return Ok(scaled);
+        }
+
+        for y in 0..height {
+            let source_y = y * self.height / height;
+            for x in 0..width {
+                let source_x = x * self.width / width;
+                scaled.pixels[y * width + x] = self.pixels[source_y * self.width + source_x];
+            }
+        }
+
+        Ok(scaled)
+    }
+
+    /// Returns a copy of this image scaled to fit within the given dimensions
+    ///
+    /// The aspect ratio of the image is preserved.
+    pub fn scale_to_fit<'b>(
+        &self,
+        width: usize,
+        height: usize,
+        boot_services: &'b BootServices
+    ) -> Result<Image<'b>, Status> {
+
+        if self.width == 0 || self.height == 0 {
+            return Image::allocate(0, 0, boot_services);
+        }
+
+        // Compare width / self.width against height / self.height without losing precision
+        let (width, height) = if width * self.height <= height * self.width {
+            (width, cmp::max(1, self.height * width / self.width))
+        } else {
+            (cmp::max(1, self.width * height / self.height), height)
+        };
+
+        self.scale(width, height, boot_services)
+    }
+
+    /// Draws the image onto the screen with its top left corner at the given position
+    pub fn draw(&self, output: &GraphicsOutput, position: (usize, usize)) -> Result<(), Status> {
+
+        output.blt_buffer_to_video(
+            &self.pixels,
+            self.width,
+            (0, 0),
+            position,
+            (self.width, self.height)
+        )
+    }
+
+    /// Draws the image in the center of the screen
+    pub fn draw_centered(&self, output: &GraphicsOutput) -> Result<(), Status> {
+
+        let info = output.current_mode();
+        let position = self.centered(
+            info.horizontal_resolution as usize,
+            info.vertical_resolution as usize
+        );
+
+        self.draw(output, position)
+    }
+
+    /// Writes the image directly into a framebuffer with its top left corner at the given position
+    ///
+    /// Pixels are converted to the framebuffer's pixel format, and any portion of the image
+    /// outside the visible area is clipped.
+    pub fn draw_to_frame_buffer(&self, frame_buffer: &mut FrameBuffer, position: (usize, usize)) {
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = self.pixels[y * self.width + x];
+                frame_buffer.write_pixel(position.0 + x, position.1 + y, pixel);
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! QOI decoding
+//!
+//! See https://qoiformat.org/qoi-specification.pdf for a description of the format.
+
+
+use boot_services::BootServices;
+use io::Read;
+use protocols::BltPixel;
+use types::Status;
+use super::Image;
+
+
+/// Size of the header which precedes the encoded pixels
+const HEADER_SIZE: usize = 14;
+
+/// Chunk tags
+const QOI_OP_INDEX: u8 = 0x00;
+const QOI_OP_DIFF: u8 = 0x40;
+const QOI_OP_LUMA: u8 = 0x80;
+const QOI_OP_RUN: u8 = 0xc0;
+const QOI_OP_RGB: u8 = 0xfe;
+const QOI_OP_RGBA: u8 = 0xff;
+const QOI_MASK_2: u8 = 0xc0;
+
+
+/// Tells whether the data looks like a QOI file
+pub(super) fn is_qoi(data: &[u8]) -> bool {
+
+    data.starts_with(b"qoif")
+}
+
+
+/// Decodes a QOI image
+pub(super) fn decode<'a>(
+    data: &[u8],
+    boot_services: &'a BootServices
+) -> Result<Image<'a>, Status> {
+
+    let mut header = data.get(4..HEADER_SIZE).ok_or(Status::InvalidParameter)?;
+    let width = header.read_u32_be()? as usize;
+    let height = header.read_u32_be()? as usize;
+    let mut image = Image::allocate(width, height, boot_services)?;
+
+    let mut index = [BltPixel::default(); 64];
+    let mut pixel = BltPixel { blue: 0, green: 0, red: 0, reserved: 0xff };
+    let mut run = 0;
+    let mut pos = HEADER_SIZE;
+    let next = |pos: &mut usize| -> Result<u8, Status> {
+        let byte = *data.get(*pos).ok_or(Status::InvalidParameter)?;
+        *pos += 1;
+        Ok(byte)
+    };
+
+    for out in image.pixels.iter_mut() {
+        if run > 0 {
+            run -= 1;
+            *out = pixel;
+            continue;
+        }
+
+        let tag = next(&mut pos)?;
+        if tag == QOI_OP_RGB {
+            pixel.red = next(&mut pos)?;
+            pixel.green = next(&mut pos)?;
+            pixel.blue = next(&mut pos)?;
+        } else if tag == QOI_OP_RGBA {
+            pixel.red = next(&mut pos)?;
+            pixel.green = next(&mut pos)?;
+            pixel.blue = next(&mut pos)?;
+            pixel.reserved = next(&mut pos)?;
+        } else {
+            match tag & QOI_MASK_2 {
+                QOI_OP_INDEX => pixel = index[(tag & 0x3f) as usize],
+                QOI_OP_DIFF => {
+                    pixel.red = pixel.red.wrapping_add(((tag >> 4) & 0x03).wrapping_sub(2));
+                    pixel.green = pixel.green.wrapping_add(((tag >> 2) & 0x03).wrapping_sub(2));
+                    pixel.blue = pixel.blue.wrapping_add((tag & 0x03).wrapping_sub(2));
+                },
+                QOI_OP_LUMA => {
+                    let byte = next(&mut pos)?;
+                    let dg = (tag & 0x3f).wrapping_sub(32);
+                    pixel.red = pixel.red
+                        .wrapping_add(dg.wrapping_sub(8).wrapping_add(byte >> 4));
+                    pixel.green = pixel.green.wrapping_add(dg);
+                    pixel.blue = pixel.blue
+                        .wrapping_add(dg.wrapping_sub(8).wrapping_add(byte & 0x0f));
+                },
+                QOI_OP_RUN => run = (tag & 0x3f) as usize,
+                _ => unreachable!(),
+            }
+        }
+
+        index[hash(pixel)] = pixel;
+        *out = pixel;
+    }
+
+    Ok(image)
+}
+
+
+/// Returns the position of a pixel in the array of previously seen pixels
+fn hash(pixel: BltPixel) -> usize {
+
+    (pixel.red as usize * 3 +
+        pixel.green as usize * 5 +
+        pixel.blue as usize * 7 +
+        pixel.reserved as usize * 11) % 64
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::vec::Vec;
+    use boot_services::testing;
+    use protocols::BltPixel;
+    use types::Status;
+    use super::*;
+
+    fn header(width: u32, height: u32) -> Vec<u8> {
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"qoif");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data.extend_from_slice(&[4, 0]);
+        data
+    }
+
+    fn rgb(red: u8, green: u8, blue: u8) -> BltPixel {
+
+        BltPixel { blue: blue, green: green, red: red, reserved: 0xff }
+    }
+
+    #[test]
+    fn decodes_each_chunk_type() {
+
+        let mut data = header(3, 2);
+        data.extend_from_slice(&[QOI_OP_RGB, 10, 20, 30]);
+        // Run of one more pixel
+        data.push(QOI_OP_RUN);
+        // Red +1, green +0, blue -1
+        data.push(QOI_OP_DIFF | 3 << 4 | 2 << 2 | 1);
+        // The first pixel, which hashes to 9
+        data.push(QOI_OP_INDEX | 9);
+        // Green +4, red +5, blue +3
+        data.extend_from_slice(&[QOI_OP_LUMA | (32 + 4), (8 + 1) << 4 | (8 - 1)]);
+        data.extend_from_slice(&[QOI_OP_RGBA, 1, 2, 3, 4]);
+
+        let image = decode(&data, testing::boot_services()).unwrap();
+        assert_eq!(image.width(), 3);
+        assert_eq!(image.height(), 2);
+        assert_eq!(image.pixels(), &[
+            rgb(10, 20, 30),
+            rgb(10, 20, 30),
+            rgb(11, 20, 29),
+            rgb(10, 20, 30),
+            rgb(15, 24, 33),
+            BltPixel { blue: 3, green: 2, red: 1, reserved: 4 },
+        ]);
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+
+        let mut data = header(2, 1);
+        data.extend_from_slice(&[QOI_OP_RGB, 10, 20]);
+
+        let res = decode(&data, testing::boot_services());
+        assert_eq!(res.err(), Some(Status::InvalidParameter));
+    }
+
+    #[test]
+    fn rejects_overflowing_dimensions() {
+
+        let data = header(0xffff_ffff, 0xffff_ffff);
+
+        let res = decode(&data, testing::boot_services());
+        assert_eq!(res.err(), Some(Status::InvalidParameter));
+    }
+}
@@ -0,0 +1,643 @@
+//! Traits for reading and writing byte streams
+//!
+//! These mirror the traits of `std::io`, but report errors as a `Status` and never allocate, so
+//! they can be used both by firmware protocols and by in-memory buffers. Code which is written
+//! against them can be exercised on the host by substituting a `Cursor` for a device.
+
+
+use core::{
+    cmp,
+    mem,
+};
+use types::Status;
+
+
+/// Source of bytes
+pub trait Read {
+
+    /// Reads bytes into `buf`, returning the number of bytes read
+    ///
+    /// A return value of 0 indicates the end of the stream (unless `buf` is empty).
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status>;
+
+    /// Reads exactly enough bytes to fill `buf`
+    ///
+    /// Fails with `Status::EndOfFile` if the stream ends first.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<(), Status> {
+
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Status::EndOfFile),
+                len => buf = &mut mem::replace(&mut buf, &mut [])[len..],
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a little-endian u16
+    fn read_u16_le(&mut self) -> Result<u16, Status> {
+
+        let mut bytes = [0; 2];
+        self.read_exact(&mut bytes)?;
+        Ok(bytes[0] as u16 | (bytes[1] as u16) << 8)
+    }
+
+    /// Reads a little-endian u32
+    fn read_u32_le(&mut self) -> Result<u32, Status> {
+
+        Ok(self.read_u16_le()? as u32 | (self.read_u16_le()? as u32) << 16)
+    }
+
+    /// Reads a little-endian u64
+    fn read_u64_le(&mut self) -> Result<u64, Status> {
+
+        Ok(self.read_u32_le()? as u64 | (self.read_u32_le()? as u64) << 32)
+    }
+
+    /// Reads a big-endian u32
+    fn read_u32_be(&mut self) -> Result<u32, Status> {
+
+        let mut bytes = [0; 4];
+        self.read_exact(&mut bytes)?;
+        Ok(
+            (bytes[0] as u32) << 24 | (bytes[1] as u32) << 16 | (bytes[2] as u32) << 8
+                | bytes[3] as u32
+        )
+    }
+}
+
+
+/// Destination for bytes
+pub trait Write {
+
+    /// Writes bytes from `buf`, returning the number of bytes written
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status>;
+
+    /// Ensures that all written bytes have reached their destination
+    fn flush(&mut self) -> Result<(), Status>;
+
+    /// Writes all of `buf`
+    ///
+    /// Fails with `Status::VolumeFull` if the destination stops accepting bytes.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), Status> {
+
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Status::VolumeFull),
+                len => buf = &buf[len..],
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Position to seek to within a stream
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SeekFrom {
+    Start(u64),
+    End(i64),
+    Current(i64),
+}
+
+
+/// Stream with a movable position
+pub trait Seek {
+
+    /// Moves to a new position, returning it as an offset from the start of the stream
+    ///
+    /// Fails with `Status::InvalidParameter` if the new position would be before the start.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status>;
+
+    /// Returns the current position as an offset from the start of the stream
+    fn stream_position(&mut self) -> Result<u64, Status> {
+
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+
+/// Source of bytes with an internal buffer
+pub trait BufRead: Read {
+
+    /// Returns the buffered bytes, reading more from the underlying source if none are left
+    ///
+    /// An empty slice indicates the end of the stream.
+    fn fill_buf(&mut self) -> Result<&[u8], Status>;
+
+    /// Marks bytes returned by `fill_buf` as read
+    fn consume(&mut self, amt: usize);
+
+    /// Reads bytes into `buf` up to and including `delim`, returning the number of bytes read
+    ///
+    /// Fewer bytes are read, without `delim`, if the stream ends first. Fails with
+    /// `Status::BufferTooSmall` if `buf` fills up before `delim` is found; the bytes read so far
+    /// remain in `buf` and are consumed.
+    fn read_until(&mut self, delim: u8, buf: &mut [u8]) -> Result<usize, Status> {
+
+        let mut read = 0;
+        loop {
+            let (done, used) = {
+                let available = self.fill_buf()?;
+                if available.is_empty() {
+                    return Ok(read);
+                }
+
+                let (done, len) = match available.iter().position(|b| *b == delim) {
+                    Some(i) => (true, i + 1),
+                    None => (false, available.len()),
+                };
+                let copied = cmp::min(len, buf.len() - read);
+                buf[read..read + copied].copy_from_slice(&available[..copied]);
+                read += copied;
+                if copied < len {
+                    (None, copied)
+                } else {
+                    (Some(done), copied)
+                }
+            };
+            self.consume(used);
+
+            match done {
+                Some(true) => return Ok(read),
+                Some(false) => { },
+                None => return Err(Status::BufferTooSmall),
+            }
+        }
+    }
+}
+
+
+impl<'a, R: Read + ?Sized> Read for &'a mut R {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        (**self).read(buf)
+    }
+}
+
+impl<'a, W: Write + ?Sized> Write for &'a mut W {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        (**self).flush()
+    }
+}
+
+impl<'a, S: Seek + ?Sized> Seek for &'a mut S {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        (**self).seek(pos)
+    }
+}
+
+impl<'a, B: BufRead + ?Sized> BufRead for &'a mut B {
+
+    fn fill_buf(&mut self) -> Result<&[u8], Status> {
+
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+
+        (**self).consume(amt)
+    }
+}
+
+
+/// Reading from a slice advances it past the bytes read
+impl<'a> Read for &'a [u8] {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        let len = cmp::min(buf.len(), self.len());
+        buf[..len].copy_from_slice(&self[..len]);
+        *self = &self[len..];
+        Ok(len)
+    }
+}
+
+impl<'a> BufRead for &'a [u8] {
+
+    fn fill_buf(&mut self) -> Result<&[u8], Status> {
+
+        Ok(*self)
+    }
+
+    fn consume(&mut self, amt: usize) {
+
+        *self = &self[cmp::min(amt, self.len())..];
+    }
+}
+
+/// Writing to a slice advances it past the bytes written
+impl<'a> Write for &'a mut [u8] {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        let len = cmp::min(buf.len(), self.len());
+        let (head, tail) = mem::replace(self, &mut []).split_at_mut(len);
+        head.copy_from_slice(&buf[..len]);
+        *self = tail;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        Ok(())
+    }
+}
+
+
+/// Adds a position to an in-memory buffer so that it can be used as a stream
+///
+/// Writes never grow the buffer; they stop at its end.
+#[derive(Clone, Debug, Default)]
+pub struct Cursor<T> {
+    inner: T,
+    pos: u64,
+}
+
+impl<T> Cursor<T> {
+
+    /// Creates a cursor at the start of a buffer
+    pub fn new(inner: T) -> Cursor<T> {
+
+        Cursor {
+            inner: inner,
+            pos: 0,
+        }
+    }
+
+    /// Returns the underlying buffer
+    pub fn into_inner(self) -> T {
+
+        self.inner
+    }
+
+    /// Returns a reference to the underlying buffer
+    pub fn get_ref(&self) -> &T {
+
+        &self.inner
+    }
+
+    /// Returns the current position
+    pub fn position(&self) -> u64 {
+
+        self.pos
+    }
+
+    /// Sets the current position, which may be past the end of the buffer
+    pub fn set_position(&mut self, pos: u64) {
+
+        self.pos = pos;
+    }
+}
+
+impl<T: AsRef<[u8]>> Cursor<T> {
+
+    /// Returns the bytes from the current position to the end of the buffer
+    fn remaining(&self) -> &[u8] {
+
+        let data = self.inner.as_ref();
+        &data[cmp::min(self.pos, data.len() as u64) as usize..]
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for Cursor<T> {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        let len = self.remaining().read(buf)?;
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<T: AsRef<[u8]>> BufRead for Cursor<T> {
+
+    fn fill_buf(&mut self) -> Result<&[u8], Status> {
+
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+
+        self.pos += amt as u64;
+    }
+}
+
+impl<T: AsRef<[u8]>> Seek for Cursor<T> {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        self.pos = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => offset_position(self.inner.as_ref().len() as u64, offset)?,
+            SeekFrom::Current(offset) => offset_position(self.pos, offset)?,
+        };
+        Ok(self.pos)
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Write for Cursor<T> {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        let data = self.inner.as_mut();
+        let start = cmp::min(self.pos, data.len() as u64) as usize;
+        let len = (&mut data[start..]).write(buf)?;
+        self.pos += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        Ok(())
+    }
+}
+
+
+/// Adds buffering to a source of bytes
+///
+/// The buffer is provided by the caller, so that no allocation is needed.
+#[derive(Debug)]
+pub struct BufReader<'b, R> {
+    inner: R,
+    buf: &'b mut [u8],
+    pos: usize,
+    filled: usize,
+}
+
+impl<'b, R: Read> BufReader<'b, R> {
+
+    /// Creates a reader which buffers up to `buf.len()` bytes at a time
+    pub fn new(inner: R, buf: &'b mut [u8]) -> BufReader<'b, R> {
+
+        BufReader {
+            inner: inner,
+            buf: buf,
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying reader
+    pub fn get_ref(&self) -> &R {
+
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader
+    ///
+    /// Reading from it directly will cause buffered bytes to be skipped.
+    pub fn get_mut(&mut self) -> &mut R {
+
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader, discarding any buffered bytes
+    pub fn into_inner(self) -> R {
+
+        self.inner
+    }
+
+    /// Returns the bytes which have been buffered but not yet read
+    pub fn buffer(&self) -> &[u8] {
+
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Discards the buffered bytes
+    fn discard_buffer(&mut self) {
+
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+impl<'b, R: Read> Read for BufReader<'b, R> {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        // Large reads bypass the buffer when it is empty
+        if self.pos == self.filled && buf.len() >= self.buf.len() {
+            return self.inner.read(buf);
+        }
+
+        let len = self.fill_buf()?.read(buf)?;
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<'b, R: Read> BufRead for BufReader<'b, R> {
+
+    fn fill_buf(&mut self) -> Result<&[u8], Status> {
+
+        if self.pos == self.filled {
+            self.filled = self.inner.read(self.buf)?;
+            self.pos = 0;
+        }
+
+        Ok(&self.buf[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+
+        self.pos = cmp::min(self.pos + amt, self.filled);
+    }
+}
+
+impl<'b, R: Read + Seek> Seek for BufReader<'b, R> {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        // The underlying reader is ahead of the logical position by the number of buffered bytes
+        let buffered = (self.filled - self.pos) as i64;
+        let pos = match pos {
+            SeekFrom::Current(offset) => {
+                SeekFrom::Current(offset.checked_sub(buffered).ok_or(Status::InvalidParameter)?)
+            },
+            pos => pos,
+        };
+
+        // The buffer is kept if the seek fails, so that the position is unchanged
+        let position = self.inner.seek(pos)?;
+        self.discard_buffer();
+        Ok(position)
+    }
+}
+
+
+/// Applies a signed offset to a position
+pub(crate) fn offset_position(base: u64, offset: i64) -> Result<u64, Status> {
+
+    let position = if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.wrapping_neg() as u64)
+    };
+
+    position.ok_or(Status::InvalidParameter)
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Source which returns at most three bytes per read
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+            let len = cmp::min(buf.len(), 3);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn reads_integers() {
+
+        let mut data: &[u8] = &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18];
+        assert_eq!(data.read_u16_le(), Ok(0x0201));
+        assert_eq!(data.read_u32_le(), Ok(0x0605_0403));
+        assert_eq!(data.read_u64_le(), Ok(0x0e0d_0c0b_0a09_0807));
+        assert_eq!(data.read_u32_be(), Ok(0x0f10_1112));
+        assert_eq!(data.read_u16_le(), Err(Status::EndOfFile));
+    }
+
+    #[test]
+    fn read_exact_retries_short_reads() {
+
+        let mut source = Trickle(b"0123456789");
+        let mut buf = [0; 8];
+        assert_eq!(source.read_exact(&mut buf), Ok(()));
+        assert_eq!(&buf, b"01234567");
+        assert_eq!(source.read_exact(&mut buf), Err(Status::EndOfFile));
+    }
+
+    #[test]
+    fn cursor_reads_and_writes() {
+
+        let mut cursor = Cursor::new([0u8; 8]);
+        assert_eq!(cursor.write(b"abcdef"), Ok(6));
+        // Writes stop at the end of the buffer
+        assert_eq!(cursor.write(b"ghij"), Ok(2));
+        assert_eq!(cursor.write_all(b"k"), Err(Status::VolumeFull));
+        assert_eq!(cursor.get_ref(), b"abcdefgh");
+
+        cursor.set_position(2);
+        let mut buf = [0; 4];
+        assert_eq!(cursor.read(&mut buf), Ok(4));
+        assert_eq!(&buf, b"cdef");
+        assert_eq!(cursor.fill_buf(), Ok(&b"gh"[..]));
+        cursor.consume(1);
+        assert_eq!(cursor.position(), 7);
+    }
+
+    #[test]
+    fn cursor_seeks() {
+
+        let mut cursor = Cursor::new(&b"0123456789"[..]);
+        assert_eq!(cursor.seek(SeekFrom::End(-3)), Ok(7));
+        assert_eq!(cursor.seek(SeekFrom::Current(-2)), Ok(5));
+        assert_eq!(cursor.seek(SeekFrom::Current(-6)), Err(Status::InvalidParameter));
+        assert_eq!(cursor.seek(SeekFrom::End(-11)), Err(Status::InvalidParameter));
+        assert_eq!(cursor.stream_position(), Ok(5));
+
+        // Seeking past the end is allowed, after which reads return nothing
+        assert_eq!(cursor.seek(SeekFrom::Start(20)), Ok(20));
+        let mut buf = [0; 4];
+        assert_eq!(cursor.read(&mut buf), Ok(0));
+        assert_eq!(cursor.seek(SeekFrom::End(2)), Ok(12));
+    }
+
+    #[test]
+    fn buf_reader_reads_lines() {
+
+        let mut buf = [0; 4];
+        let mut reader = BufReader::new(Trickle(b"one\ntwo\nthree"), &mut buf);
+        let mut line = [0; 8];
+        assert_eq!(reader.read_until(b'\n', &mut line), Ok(4));
+        assert_eq!(&line[..4], b"one\n");
+        assert_eq!(reader.read_until(b'\n', &mut line), Ok(4));
+        assert_eq!(&line[..4], b"two\n");
+        assert_eq!(reader.read_until(b'\n', &mut line[..3]), Err(Status::BufferTooSmall));
+        assert_eq!(&line[..3], b"thr");
+        assert_eq!(reader.read_until(b'\n', &mut line), Ok(2));
+        assert_eq!(&line[..2], b"ee");
+        assert_eq!(reader.read_until(b'\n', &mut line), Ok(0));
+    }
+
+    #[test]
+    fn buf_reader_seeks_from_logical_position() {
+
+        let mut buf = [0; 4];
+        let mut reader = BufReader::new(Cursor::new(&b"0123456789"[..]), &mut buf);
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(reader.buffer(), b"123");
+
+        // The underlying cursor is at 4, but the reader is logically at 1
+        assert_eq!(reader.seek(SeekFrom::Current(2)), Ok(3));
+        assert!(reader.buffer().is_empty());
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(&byte, b"3");
+
+        assert_eq!(reader.seek(SeekFrom::Current(-5)), Err(Status::InvalidParameter));
+        assert_eq!(reader.stream_position(), Ok(4));
+        assert_eq!(reader.seek(SeekFrom::End(-1)), Ok(9));
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(&byte, b"9");
+    }
+
+    #[test]
+    fn buf_reader_keeps_buffer_when_seek_fails() {
+
+        let mut buf = [0; 4];
+        let mut reader = BufReader::new(Cursor::new(&b"0123456789"[..]), &mut buf);
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte).unwrap();
+
+        assert_eq!(reader.seek(SeekFrom::Current(-2)), Err(Status::InvalidParameter));
+        assert_eq!(reader.seek(SeekFrom::Current(i64::min_value())), Err(Status::InvalidParameter));
+        assert_eq!(reader.buffer(), b"123");
+        reader.read_exact(&mut byte).unwrap();
+        assert_eq!(&byte, b"1");
+    }
+
+    #[test]
+    fn buf_reader_bypasses_buffer_for_large_reads() {
+
+        let mut buf = [0; 2];
+        let mut reader = BufReader::new(&b"0123456789"[..], &mut buf);
+        let mut large = [0; 6];
+        assert_eq!(reader.read(&mut large), Ok(6));
+        assert!(reader.buffer().is_empty());
+        assert_eq!(reader.get_ref(), b"6789");
+    }
+
+    #[test]
+    fn offsets_positions() {
+
+        assert_eq!(offset_position(10, 5), Ok(15));
+        assert_eq!(offset_position(10, -10), Ok(0));
+        assert_eq!(offset_position(10, -11), Err(Status::InvalidParameter));
+        assert_eq!(offset_position(u64::max_value(), 1), Err(Status::InvalidParameter));
+        let max = u64::max_value();
+        assert_eq!(offset_position(max, i64::min_value()), Ok(i64::max_value() as u64));
+    }
+}
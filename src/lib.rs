@@ -9,27 +9,60 @@
 #[macro_use]
 extern crate bitflags;
 extern crate rlibc;
+#[cfg(test)]
+extern crate std;
+
+#[cfg(feature = "boot-services")]
+use core::fmt;
 
 
 #[cfg(feature = "boot-services")]
 pub mod boot_services;
 #[cfg(feature = "boot-services")]
+pub mod driver;
+#[cfg(feature = "boot-services")]
+pub mod fb_console;
+#[cfg(feature = "boot-services")]
+pub mod fs;
+#[cfg(feature = "boot-services")]
+pub mod image;
+pub mod io;
+#[cfg(feature = "boot-services")]
+pub mod line_editor;
+#[cfg(feature = "boot-services")]
+pub mod partition;
+#[cfg(feature = "boot-services")]
 pub mod protocols;
 pub mod runtime_services;
 mod system_table;
 pub mod types;
+#[cfg(feature = "boot-services")]
+pub mod volume;
 
 pub use system_table::SystemTable;
 
 
+/// Destination for text printed with `efi_print!` and `efi_println!`
+///
+/// This is implemented for `&SystemTable`, which prints to `con_out`, and for `&mut FbConsole`.
+#[cfg(feature = "boot-services")]
+pub trait PrintTarget {
+
+    /// Writes formatted text to this target
+    fn print_fmt(self, args: fmt::Arguments) -> fmt::Result;
+}
+
+
 /// Print text to the console
+///
+/// The first argument is anything which implements `PrintTarget`, such as the system table.
 #[cfg(feature = "boot-services")]
 #[macro_export]
 macro_rules! efi_print {
-    ($system_table:expr, $($arg:tt)*) => ({
-        use core::fmt::Write;
-        (&*($system_table).con_out)
-            .write_fmt(format_args!($($arg)*))
+    ($target:expr, $($arg:tt)*) => ({
+        use $crate::PrintTarget;
+        ($target)
+            .print_fmt(format_args!($($arg)*))
             .expect("could not write to console");
     });
 }
@@ -39,8 +72,8 @@ macro_rules! efi_print {
 #[cfg(feature = "boot-services")]
 #[macro_export]
 macro_rules! efi_println {
-    ($system_table:expr, $fmt:expr) =>
-        (efi_print!($system_table, concat!($fmt, "\r\n")));
-    ($system_table:expr, $fmt:expr, $($arg:tt)*) =>
-        (efi_print!($system_table, concat!($fmt, "\r\n"), $($arg)*));
+    ($target:expr, $fmt:expr) =>
+        (efi_print!($target, concat!($fmt, "\r\n")));
+    ($target:expr, $fmt:expr, $($arg:tt)*) =>
+        (efi_print!($target, concat!($fmt, "\r\n"), $($arg)*));
 }
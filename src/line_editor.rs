@@ -0,0 +1,383 @@
+//! Interactive line editing on top of the console protocols
+//!
+//! `LineEditor` reads a single line of text from a `SimpleTextInput` device, echoing it to a
+//! `SimpleTextOutput` device while supporting cursor movement, history and tab completion.
+
+
+use core::{
+    char,
+    cmp,
+    str,
+};
+use {
+    boot_services::{
+        BootServices,
+        Pool,
+    },
+    protocols::{
+        InputKey,
+        ScanCode,
+        SimpleTextInput,
+        SimpleTextOutput,
+    },
+    types::{
+        Char16,
+        Status,
+    },
+};
+
+
+const CHAR_BACKSPACE: Char16 = 0x0008;
+const CHAR_TAB: Char16 = 0x0009;
+const CHAR_LINEFEED: Char16 = 0x000a;
+const CHAR_CARRIAGE_RETURN: Char16 = 0x000d;
+
+
+/// Provides completions for a partially-entered line
+pub trait Completer {
+
+    /// Returns the text to insert at the cursor, given the portion of the line before the cursor
+    fn complete(&mut self, line: &str) -> Option<&str>;
+}
+
+
+/// Reads lines of text from the console
+///
+/// The line being edited and the history are stored in pool memory allocated when the editor is
+/// created, so no allocation happens while a line is read. Each line is limited to a fixed number
+/// of bytes of UTF-8; further input is ignored once the line is full.
+pub struct LineEditor<'a> {
+    input: &'a SimpleTextInput,
+    output: &'a SimpleTextOutput,
+    boot_services: &'a BootServices,
+
+    // Line being edited, as UTF-8
+    buf: Pool<'a, [u8]>,
+    len: usize,
+    cursor: usize,
+
+    // Ring of previously-entered lines, each occupying a slot of buf.len() bytes
+    history: Pool<'a, [u8]>,
+    history_lens: Pool<'a, [usize]>,
+    history_count: usize,
+    history_next: usize,
+
+    mask: Option<char>,
+    completer: Option<&'a mut dyn Completer>,
+
+    // Screen state of the line being edited
+    start_column: usize,
+    start_row: usize,
+    columns: usize,
+    displayed: usize,
+}
+
+impl<'a> LineEditor<'a> {
+
+    /// Creates a new line editor
+    ///
+    /// `max_len` is the maximum length of a line in bytes, and `history_len` is the number of
+    /// previous lines that are remembered.
+    pub fn new(
+        input: &'a SimpleTextInput,
+        output: &'a SimpleTextOutput,
+        boot_services: &'a BootServices,
+        max_len: usize,
+        history_len: usize
+    ) -> Result<LineEditor<'a>, Status> {
+
+        let buf = boot_services.allocate_slice(max_len)?;
+        let history = boot_services.allocate_slice(max_len * history_len)?;
+        let mut history_lens = boot_services.allocate_slice(history_len)?;
+        for len in history_lens.iter_mut() {
+            *len = 0;
+        }
+
+        Ok(LineEditor {
+            input: input,
+            output: output,
+            boot_services: boot_services,
+            buf: buf,
+            len: 0,
+            cursor: 0,
+            history: history,
+            history_lens: history_lens,
+            history_count: 0,
+            history_next: 0,
+            mask: None,
+            completer: None,
+            start_column: 0,
+            start_row: 0,
+            columns: 0,
+            displayed: 0,
+        })
+    }
+
+    /// Sets a character to display in place of each entered character
+    ///
+    /// While a mask is set, entered lines are not added to the history.
+    pub fn set_mask(&mut self, mask: Option<char>) {
+
+        self.mask = mask;
+    }
+
+    /// Sets the completer which is consulted when Tab is pressed
+    pub fn set_completer(&mut self, completer: Option<&'a mut dyn Completer>) {
+
+        self.completer = completer;
+    }
+
+    /// Reads a line of text, returning once Enter is pressed
+    ///
+    /// Editing starts at the current cursor position. The returned line does not include the line
+    /// terminator.
+    pub fn read_line(&mut self) -> Result<&str, Status> {
+
+        self.len = 0;
+        self.cursor = 0;
+        self.displayed = 0;
+        let (start_column, start_row) = self.output.cursor_position();
+        self.start_column = start_column;
+        self.start_row = start_row;
+        self.columns = self.output.query_mode(self.output.mode.mode as usize)?.columns;
+
+        // Make sure the cursor is visible while editing, then restore its previous state
+        let cursor_visible = self.output.mode.cursor_visible();
+        let _ = self.output.enable_cursor(true);
+        let res = self.edit();
+        let _ = self.output.enable_cursor(cursor_visible);
+        res?;
+
+        if self.mask.is_none() && self.len > 0 {
+            self.push_history();
+        }
+
+        Ok(self.line())
+    }
+
+    /// Processes keystrokes until the line is entered
+    fn edit(&mut self) -> Result<(), Status> {
+
+        // Position in the history, where 0 is the most recent entry
+        let mut history_pos: Option<usize> = None;
+
+        loop {
+            let key = self.input.read_key_blocking(self.boot_services)?;
+            match key {
+                InputKey { unicode_char: CHAR_CARRIAGE_RETURN, .. } |
+                InputKey { unicode_char: CHAR_LINEFEED, .. } => break,
+                InputKey { unicode_char: CHAR_BACKSPACE, .. } => {
+                    if self.cursor > 0 {
+                        self.cursor = self.prev_boundary(self.cursor);
+                        self.remove_at(self.cursor);
+                    }
+                },
+                InputKey { unicode_char: CHAR_TAB, .. } => self.complete(),
+                InputKey { scan_code: ScanCode::Null, unicode_char } => {
+                    match char::from_u32(unicode_char as u32) {
+                        Some(c) if !c.is_control() => self.insert(c),
+                        _ => { },
+                    }
+                },
+                InputKey { scan_code: ScanCode::CursorLeft, .. } => {
+                    self.cursor = self.prev_boundary(self.cursor);
+                },
+                InputKey { scan_code: ScanCode::CursorRight, .. } => {
+                    self.cursor = self.next_boundary(self.cursor);
+                },
+                InputKey { scan_code: ScanCode::Home, .. } => self.cursor = 0,
+                InputKey { scan_code: ScanCode::End, .. } => self.cursor = self.len,
+                InputKey { scan_code: ScanCode::Delete, .. } => {
+                    if self.cursor < self.len {
+                        self.remove_at(self.cursor);
+                    }
+                },
+                InputKey { scan_code: ScanCode::CursorUp, .. } => {
+                    let pos = history_pos.map_or(0, |p| p + 1);
+                    if pos < self.history_count {
+                        self.load_history(pos);
+                        history_pos = Some(pos);
+                    }
+                },
+                InputKey { scan_code: ScanCode::CursorDown, .. } => {
+                    match history_pos {
+                        Some(0) => {
+                            self.len = 0;
+                            self.cursor = 0;
+                            history_pos = None;
+                        },
+                        Some(pos) => {
+                            self.load_history(pos - 1);
+                            history_pos = Some(pos - 1);
+                        },
+                        None => { },
+                    }
+                },
+                _ => { },
+            }
+
+            self.redraw()?;
+        }
+
+        // Leave the cursor on the line following the entered text
+        self.cursor = self.len;
+        self.redraw()?;
+        self.output.output_string("\r\n")?;
+        Ok(())
+    }
+
+    /// Returns the line being edited
+    fn line(&self) -> &str {
+
+        // The buffer is only ever modified a whole character at a time
+        unsafe { str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+
+    /// Returns the byte offset of the character preceding `index`
+    fn prev_boundary(&self, index: usize) -> usize {
+
+        self.line()[..index]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    /// Returns the byte offset of the character following `index`
+    fn next_boundary(&self, index: usize) -> usize {
+
+        self.line()[index..]
+            .chars()
+            .next()
+            .map_or(index, |c| index + c.len_utf8())
+    }
+
+    /// Inserts a character at the cursor, if there is room for it
+    fn insert(&mut self, c: char) {
+
+        let width = c.len_utf8();
+        if self.len + width > self.buf.len() {
+            return;
+        }
+
+        let cursor = self.cursor;
+        for i in (cursor..self.len).rev() {
+            self.buf[i + width] = self.buf[i];
+        }
+        c.encode_utf8(&mut self.buf[cursor..cursor + width]);
+        self.len += width;
+        self.cursor += width;
+    }
+
+    /// Removes the character starting at byte offset `index`
+    fn remove_at(&mut self, index: usize) {
+
+        let width = self.next_boundary(index) - index;
+        for i in index..self.len - width {
+            self.buf[i] = self.buf[i + width];
+        }
+        self.len -= width;
+    }
+
+    /// Inserts the completion for the text preceding the cursor
+    fn complete(&mut self) {
+
+        if let Some(completer) = self.completer.take() {
+            let completion = {
+                let prefix = unsafe { str::from_utf8_unchecked(&self.buf[..self.cursor]) };
+                completer.complete(prefix)
+            };
+            if let Some(completion) = completion {
+                for c in completion.chars() {
+                    self.insert(c);
+                }
+            }
+            self.completer = Some(completer);
+        }
+    }
+
+    /// Adds the current line to the history
+    fn push_history(&mut self) {
+
+        let slots = self.history_lens.len();
+        if slots == 0 {
+            return;
+        }
+
+        let slot = self.history_next;
+        let slot_len = self.buf.len();
+        self.history[slot * slot_len..slot * slot_len + self.len]
+            .copy_from_slice(&self.buf[..self.len]);
+        self.history_lens[slot] = self.len;
+        self.history_next = (slot + 1) % slots;
+        self.history_count = cmp::min(self.history_count + 1, slots);
+    }
+
+    /// Replaces the current line with an entry from the history
+    fn load_history(&mut self, pos: usize) {
+
+        let slots = self.history_lens.len();
+        let slot = (self.history_next + slots - 1 - pos) % slots;
+        let slot_len = self.buf.len();
+        let len = self.history_lens[slot];
+        self.buf[..len]
+            .copy_from_slice(&self.history[slot * slot_len..slot * slot_len + len]);
+        self.len = len;
+        self.cursor = len;
+    }
+
+    /// Redraws the line and places the cursor
+    ///
+    /// The line wraps at the edge of the screen. If the screen scrolls while the line is drawn, the
+    /// starting row is adjusted accordingly.
+    fn redraw(&mut self) -> Result<(), Status> {
+
+        self.output.set_cursor_position(self.start_column, self.start_row)?;
+
+        let chars = self.line().chars().count();
+        match self.mask {
+            Some(mask) => self.output_repeated(mask, chars)?,
+            None => { self.output.output_string(self.line())?; },
+        }
+
+        // Blank out any characters left over from a longer line
+        if self.displayed > chars {
+            self.output_repeated(' ', self.displayed - chars)?;
+        }
+
+        let end = self.start_column + cmp::max(chars, self.displayed);
+        let expected_row = self.start_row + end / self.columns;
+        let (_, actual_row) = self.output.cursor_position();
+        if actual_row < expected_row {
+            self.start_row = self.start_row.saturating_sub(expected_row - actual_row);
+        }
+        self.displayed = chars;
+
+        let offset = self.start_column + self.line()[..self.cursor].chars().count();
+        self.output.set_cursor_position(
+            offset % self.columns,
+            self.start_row + offset / self.columns
+        )
+    }
+
+    /// Outputs the same character several times
+    fn output_repeated(&self, c: char, count: usize) -> Result<(), Status> {
+
+        let mut buf = [0u8; 64];
+        let width = c.len_utf8();
+        let per_chunk = buf.len() / width;
+        for i in 0..per_chunk {
+            c.encode_utf8(&mut buf[i * width..]);
+        }
+
+        let mut remaining = count;
+        while remaining > 0 {
+            let n = cmp::min(remaining, per_chunk);
+            // The buffer only contains whole characters
+            let chunk = unsafe { str::from_utf8_unchecked(&buf[..n * width]) };
+            self.output.output_string(chunk)?;
+            remaining -= n;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,509 @@
+//! GUID partition table
+
+
+use core::{
+    char,
+    fmt,
+};
+use {
+    boot_services::Guid,
+    io::{
+        Read,
+        Seek,
+    },
+    protocols::Lba,
+    types::Status,
+};
+use super::{
+    Crc32,
+    EFI_SYSTEM_PARTITION_GUID,
+    MAX_BLOCK_SIZE,
+    UNUSED_PARTITION_GUID,
+    last_block,
+    read_block,
+    read_guid,
+};
+
+
+/// Signature at the start of a GPT header ("EFI PART")
+const GPT_SIGNATURE: u64 = 0x5452_4150_2049_4645;
+
+/// Size of the fields of a GPT header
+const MIN_HEADER_SIZE: usize = 92;
+
+/// Size of the fields of a partition entry
+const MIN_ENTRY_SIZE: usize = 128;
+
+/// Number of UTF-16 code units in a partition name
+const NAME_LEN: usize = 36;
+
+
+/// Header of a GUID partition table
+#[derive(Clone, Copy, Debug)]
+pub struct GptHeader {
+    pub revision: u32,
+    pub header_size: u32,
+    pub my_lba: Lba,
+    pub alternate_lba: Lba,
+    pub first_usable_lba: Lba,
+    pub last_usable_lba: Lba,
+    pub disk_guid: Guid,
+    pub partition_entry_lba: Lba,
+    pub number_of_partition_entries: u32,
+    pub size_of_partition_entry: u32,
+    pub partition_entry_array_crc32: u32,
+}
+
+impl GptHeader {
+
+    /// Parses a GPT header from the contents of a block, verifying its signature and CRC32
+    ///
+    /// Fails with `Status::NotFound` if the signature is missing, `Status::CrcError` if the
+    /// checksum does not match, or `Status::VolumeCorrupted` if a field is invalid.
+    pub fn parse(block: &[u8]) -> Result<GptHeader, Status> {
+
+        let mut fields = block;
+        if block.len() < MIN_HEADER_SIZE || fields.read_u64_le()? != GPT_SIGNATURE {
+            return Err(Status::NotFound);
+        }
+
+        let revision = fields.read_u32_le()?;
+        let header_size = fields.read_u32_le()? as usize;
+        if header_size < MIN_HEADER_SIZE || header_size > block.len() {
+            return Err(Status::VolumeCorrupted);
+        }
+
+        // The checksum is calculated with the checksum field set to zero
+        let header_crc32 = fields.read_u32_le()?;
+        let mut crc = Crc32::new();
+        crc.update(&block[..16]);
+        crc.update(&[0; 4]);
+        crc.update(&block[20..header_size]);
+        if crc.finish() != header_crc32 {
+            return Err(Status::CrcError);
+        }
+
+        // Skip the reserved field
+        fields.read_u32_le()?;
+        let header = GptHeader {
+            revision: revision,
+            header_size: header_size as u32,
+            my_lba: fields.read_u64_le()?,
+            alternate_lba: fields.read_u64_le()?,
+            first_usable_lba: fields.read_u64_le()?,
+            last_usable_lba: fields.read_u64_le()?,
+            disk_guid: read_guid(&mut fields)?,
+            partition_entry_lba: fields.read_u64_le()?,
+            number_of_partition_entries: fields.read_u32_le()?,
+            size_of_partition_entry: fields.read_u32_le()?,
+            partition_entry_array_crc32: fields.read_u32_le()?,
+        };
+
+        let entry_size = header.size_of_partition_entry as usize;
+        if entry_size < MIN_ENTRY_SIZE || !entry_size.is_power_of_two() {
+            return Err(Status::VolumeCorrupted);
+        }
+        if header.first_usable_lba > header.last_usable_lba {
+            return Err(Status::VolumeCorrupted);
+        }
+
+        Ok(header)
+    }
+}
+
+
+/// A GUID partition table whose header and partition entries have been verified
+#[derive(Clone, Copy, Debug)]
+pub struct Gpt {
+    header: GptHeader,
+    block_size: usize,
+}
+
+impl Gpt {
+
+    /// Reads the partition table of a disk whose blocks are `block_size` bytes
+    ///
+    /// The primary table is used if it is valid; otherwise the backup table at the end of the
+    /// disk is tried. If both are invalid, the error from the primary table is returned.
+    pub fn read<R: Read + Seek>(disk: &mut R, block_size: usize) -> Result<Gpt, Status> {
+
+        Gpt::read_primary(disk, block_size)
+            .or_else(|err| Gpt::read_backup(disk, block_size).map_err(|_| err))
+    }
+
+    /// Reads the primary partition table, which follows the protective MBR
+    pub fn read_primary<R: Read + Seek>(disk: &mut R, block_size: usize) -> Result<Gpt, Status> {
+
+        Gpt::read_at(disk, block_size, 1)
+    }
+
+    /// Reads the backup partition table from the last block of the disk
+    pub fn read_backup<R: Read + Seek>(disk: &mut R, block_size: usize) -> Result<Gpt, Status> {
+
+        let lba = last_block(disk, block_size)?;
+        Gpt::read_at(disk, block_size, lba)
+    }
+
+    /// Reads and verifies the header at the given address and the partition entries it describes
+    fn read_at<R: Read + Seek>(disk: &mut R, block_size: usize, lba: Lba) -> Result<Gpt, Status> {
+
+        let mut buf = [0; MAX_BLOCK_SIZE];
+        let header = GptHeader::parse(read_block(disk, block_size, lba, &mut buf)?)?;
+        if header.my_lba != lba {
+            return Err(Status::VolumeCorrupted);
+        }
+
+        // Entries never straddle blocks, since both sizes are powers of two
+        let entry_size = header.size_of_partition_entry as usize;
+        if entry_size > block_size {
+            return Err(Status::Unsupported);
+        }
+
+        let last_lba = last_block(disk, block_size)?;
+        let mut remaining = header.number_of_partition_entries as u64 * entry_size as u64;
+        let mut crc = Crc32::new();
+        let mut lba = header.partition_entry_lba;
+        while remaining > 0 {
+            if lba > last_lba {
+                return Err(Status::VolumeCorrupted);
+            }
+            let block = read_block(disk, block_size, lba, &mut buf)?;
+            let len = if remaining < block_size as u64 { remaining as usize } else { block_size };
+            crc.update(&block[..len]);
+            remaining -= len as u64;
+            lba += 1;
+        }
+        if crc.finish() != header.partition_entry_array_crc32 {
+            return Err(Status::CrcError);
+        }
+
+        Ok(Gpt {
+            header: header,
+            block_size: block_size,
+        })
+    }
+
+    /// Returns the header of the partition table
+    pub fn header(&self) -> &GptHeader {
+
+        &self.header
+    }
+
+    /// Returns an iterator over the partitions which are in use
+    ///
+    /// Entries are read from `disk` as the iterator advances, so it must refer to the same disk
+    /// this table was read from.
+    pub fn partitions<'r, R: Read + Seek>(&self, disk: &'r mut R) -> GptPartitions<'r, R> {
+
+        GptPartitions {
+            disk: disk,
+            header: self.header,
+            block_size: self.block_size,
+            index: 0,
+            buf: [0; MAX_BLOCK_SIZE],
+            loaded_lba: None,
+        }
+    }
+}
+
+
+/// Iterator over the partitions of a GUID partition table
+pub struct GptPartitions<'r, R: Read + Seek + 'r> {
+    disk: &'r mut R,
+    header: GptHeader,
+    block_size: usize,
+    index: u32,
+    buf: [u8; MAX_BLOCK_SIZE],
+    loaded_lba: Option<Lba>,
+}
+
+impl<'r, R: Read + Seek> Iterator for GptPartitions<'r, R> {
+    type Item = Result<GptPartition, Status>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        let block_size = self.block_size;
+        let entry_size = self.header.size_of_partition_entry as usize;
+        while self.index < self.header.number_of_partition_entries {
+            let index = self.index;
+            self.index += 1;
+
+            let offset = index as usize * entry_size;
+            let lba = self.header.partition_entry_lba + (offset / block_size) as Lba;
+            if self.loaded_lba != Some(lba) {
+                if let Err(err) = read_block(self.disk, block_size, lba, &mut self.buf) {
+                    // Stop iterating, since the remaining entries can't be read either
+                    self.index = self.header.number_of_partition_entries;
+                    return Some(Err(err));
+                }
+                self.loaded_lba = Some(lba);
+            }
+
+            let entry = &self.buf[offset % block_size..];
+            match GptPartition::parse(index, entry) {
+                Some(ref partition) if partition.type_guid == UNUSED_PARTITION_GUID => { },
+                Some(partition) => return Some(Ok(partition)),
+                None => return Some(Err(Status::VolumeCorrupted)),
+            }
+        }
+
+        None
+    }
+}
+
+
+/// A partition described by a GUID partition table
+#[derive(Clone, Copy)]
+pub struct GptPartition {
+    /// Position of the entry within the partition entry array
+    pub index: u32,
+    pub type_guid: Guid,
+    pub unique_guid: Guid,
+    pub starting_lba: Lba,
+    /// Address of the last block of the partition (inclusive)
+    pub ending_lba: Lba,
+    pub attributes: GptAttributes,
+    name: [u16; NAME_LEN],
+}
+
+impl GptPartition {
+
+    /// Parses a partition entry, which is at the given index within the partition entry array
+    pub fn parse(index: u32, entry: &[u8]) -> Option<GptPartition> {
+
+        if entry.len() < MIN_ENTRY_SIZE {
+            return None;
+        }
+
+        let mut fields = entry;
+        let type_guid = read_guid(&mut fields).ok()?;
+        let unique_guid = read_guid(&mut fields).ok()?;
+        let starting_lba = fields.read_u64_le().ok()?;
+        let ending_lba = fields.read_u64_le().ok()?;
+        let attributes = fields.read_u64_le().ok()?;
+        let mut name = [0; NAME_LEN];
+        for unit in name.iter_mut() {
+            *unit = fields.read_u16_le().ok()?;
+        }
+
+        Some(GptPartition {
+            index: index,
+            type_guid: type_guid,
+            unique_guid: unique_guid,
+            starting_lba: starting_lba,
+            ending_lba: ending_lba,
+            attributes: GptAttributes::from_bits_truncate(attributes),
+            name: name,
+        })
+    }
+
+    /// Returns the UTF-16 name of the partition, without trailing nulls
+    pub fn name(&self) -> &[u16] {
+
+        let len = self.name.iter().position(|unit| *unit == 0).unwrap_or(NAME_LEN);
+        &self.name[..len]
+    }
+
+    /// Returns the characters of the partition's name
+    ///
+    /// Invalid UTF-16 is replaced by U+FFFD.
+    pub fn name_chars<'a>(&'a self) -> impl Iterator<Item = char> + 'a {
+
+        char::decode_utf16(self.name().iter().cloned())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Returns the number of blocks in the partition
+    pub fn num_blocks(&self) -> u64 {
+
+        (self.ending_lba + 1).saturating_sub(self.starting_lba)
+    }
+
+    /// Tells whether this is an EFI system partition
+    pub fn is_esp(&self) -> bool {
+
+        self.type_guid == EFI_SYSTEM_PARTITION_GUID
+    }
+}
+
+impl fmt::Debug for GptPartition {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("GptPartition")
+            .field("index", &self.index)
+            .field("type_guid", &self.type_guid)
+            .field("unique_guid", &self.unique_guid)
+            .field("starting_lba", &self.starting_lba)
+            .field("ending_lba", &self.ending_lba)
+            .field("attributes", &self.attributes)
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+
+bitflags! {
+    /// Attributes of a GPT partition
+    pub struct GptAttributes: u64 {
+        const REQUIRED_PARTITION = 0x0000_0000_0000_0001;
+        const NO_BLOCK_IO_PROTOCOL = 0x0000_0000_0000_0002;
+        const LEGACY_BIOS_BOOTABLE = 0x0000_0000_0000_0004;
+        /// Bits whose meaning depends on the partition type
+        const TYPE_SPECIFIC = 0xffff_0000_0000_0000;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::vec::Vec;
+    use io::Cursor;
+    use partition::LINUX_FILESYSTEM_GUID;
+    use super::*;
+
+    const BLOCK_SIZE: usize = 512;
+    const NUM_BLOCKS: usize = 8;
+    const NUM_ENTRIES: u32 = 4;
+
+    fn put_guid(buf: &mut [u8], guid: &Guid) {
+
+        buf[0..4].copy_from_slice(&guid.data_1.to_le_bytes());
+        buf[4..6].copy_from_slice(&guid.data_2.to_le_bytes());
+        buf[6..8].copy_from_slice(&guid.data_3.to_le_bytes());
+        buf[8..16].copy_from_slice(&guid.data_4);
+    }
+
+    fn crc32(data: &[u8]) -> u32 {
+
+        let mut crc = Crc32::new();
+        crc.update(data);
+        crc.finish()
+    }
+
+    fn entries() -> [u8; BLOCK_SIZE] {
+
+        let mut entries = [0; BLOCK_SIZE];
+        {
+            let entry = &mut entries[0..MIN_ENTRY_SIZE];
+            put_guid(&mut entry[0..16], &EFI_SYSTEM_PARTITION_GUID);
+            entry[16] = 1;
+            entry[32..40].copy_from_slice(&3u64.to_le_bytes());
+            entry[40..48].copy_from_slice(&4u64.to_le_bytes());
+            entry[48..56].copy_from_slice(&1u64.to_le_bytes());
+            for (i, unit) in "EFI".encode_utf16().enumerate() {
+                entry[56 + i * 2..58 + i * 2].copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+        {
+            // The second entry is left unused
+            let entry = &mut entries[2 * MIN_ENTRY_SIZE..3 * MIN_ENTRY_SIZE];
+            put_guid(&mut entry[0..16], &LINUX_FILESYSTEM_GUID);
+            entry[16] = 2;
+            entry[32..40].copy_from_slice(&5u64.to_le_bytes());
+            entry[40..48].copy_from_slice(&5u64.to_le_bytes());
+        }
+        entries
+    }
+
+    fn header(my_lba: Lba, alternate_lba: Lba, partition_entry_lba: Lba) -> [u8; BLOCK_SIZE] {
+
+        let mut header = [0; BLOCK_SIZE];
+        header[0..8].copy_from_slice(&GPT_SIGNATURE.to_le_bytes());
+        header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        header[12..16].copy_from_slice(&(MIN_HEADER_SIZE as u32).to_le_bytes());
+        header[24..32].copy_from_slice(&my_lba.to_le_bytes());
+        header[32..40].copy_from_slice(&alternate_lba.to_le_bytes());
+        header[40..48].copy_from_slice(&3u64.to_le_bytes());
+        header[48..56].copy_from_slice(&5u64.to_le_bytes());
+        header[56] = 0xdd;
+        header[72..80].copy_from_slice(&partition_entry_lba.to_le_bytes());
+        header[80..84].copy_from_slice(&NUM_ENTRIES.to_le_bytes());
+        header[84..88].copy_from_slice(&(MIN_ENTRY_SIZE as u32).to_le_bytes());
+        header[88..92].copy_from_slice(&crc32(&entries()).to_le_bytes());
+        let crc = crc32(&header[..MIN_HEADER_SIZE]);
+        header[16..20].copy_from_slice(&crc.to_le_bytes());
+        header
+    }
+
+    /// Builds a disk with primary and backup tables, each describing two partitions
+    fn disk() -> Vec<u8> {
+
+        let last_lba = NUM_BLOCKS as Lba - 1;
+        let mut disk = Vec::new();
+        disk.extend_from_slice(&[0; BLOCK_SIZE]);
+        disk.extend_from_slice(&header(1, last_lba, 2));
+        disk.extend_from_slice(&entries());
+        disk.extend_from_slice(&[0; 3 * BLOCK_SIZE]);
+        disk.extend_from_slice(&entries());
+        disk.extend_from_slice(&header(last_lba, 1, last_lba - 1));
+        disk
+    }
+
+    #[test]
+    fn reads_primary_table() {
+
+        let mut disk = Cursor::new(disk());
+
+        let gpt = Gpt::read(&mut disk, BLOCK_SIZE).unwrap();
+        assert_eq!(gpt.header().my_lba, 1);
+        assert_eq!(gpt.header().disk_guid.data_1, 0xdd);
+
+        let mut partitions = gpt.partitions(&mut disk);
+        let esp = partitions.next().unwrap().unwrap();
+        assert_eq!(esp.index, 0);
+        assert!(esp.is_esp());
+        assert_eq!(esp.unique_guid.data_1, 1);
+        assert_eq!(esp.num_blocks(), 2);
+        assert_eq!(esp.attributes, GptAttributes::REQUIRED_PARTITION);
+        assert_eq!(esp.name(), &[b'E' as u16, b'F' as u16, b'I' as u16]);
+        let linux = partitions.next().unwrap().unwrap();
+        assert_eq!(linux.index, 2);
+        assert_eq!(linux.type_guid, LINUX_FILESYSTEM_GUID);
+        assert!(partitions.next().is_none());
+    }
+
+    #[test]
+    fn falls_back_to_backup_table() {
+
+        let mut data = disk();
+        // Corrupt the disk Guid of the primary header
+        data[BLOCK_SIZE + 56] ^= 0xff;
+        let mut disk = Cursor::new(data);
+
+        let res = Gpt::read_primary(&mut disk, BLOCK_SIZE);
+        assert_eq!(res.err(), Some(Status::CrcError));
+
+        let gpt = Gpt::read(&mut disk, BLOCK_SIZE).unwrap();
+        assert_eq!(gpt.header().my_lba, NUM_BLOCKS as Lba - 1);
+        assert_eq!(gpt.partitions(&mut disk).count(), 2);
+    }
+
+    #[test]
+    fn rejects_corrupted_tables() {
+
+        let mut data = disk();
+        // Corrupt the primary header, and the partition entries of the backup table
+        data[BLOCK_SIZE + 56] ^= 0xff;
+        data[(NUM_BLOCKS - 2) * BLOCK_SIZE + 32] ^= 0xff;
+
+        let res = Gpt::read(&mut Cursor::new(data), BLOCK_SIZE);
+        assert_eq!(res.err(), Some(Status::CrcError));
+    }
+
+    #[test]
+    fn rejects_missing_signature() {
+
+        let mut data = disk();
+        data[BLOCK_SIZE] = b'X';
+
+        let res = GptHeader::parse(&data[BLOCK_SIZE..2 * BLOCK_SIZE]);
+        assert_eq!(res.err(), Some(Status::NotFound));
+    }
+
+    #[test]
+    fn rejects_unsupported_block_size() {
+
+        let res = Gpt::read(&mut Cursor::new(disk()), 256);
+        assert_eq!(res.err(), Some(Status::Unsupported));
+    }
+}
@@ -0,0 +1,210 @@
+//! Master boot record
+
+
+use io::{
+    Read,
+    Seek,
+    SeekFrom,
+};
+use protocols::Lba;
+use types::Status;
+
+
+/// Size of an MBR, regardless of the disk's block size
+const MBR_SIZE: usize = 512;
+
+/// Offset of the disk signature within the MBR
+const DISK_SIGNATURE_OFFSET: usize = 440;
+
+/// Offset of the partition records within the MBR
+const PARTITION_RECORDS_OFFSET: usize = 446;
+
+/// Size of each partition record
+const PARTITION_RECORD_SIZE: usize = 16;
+
+/// Offset of the boot signature within the MBR
+const SIGNATURE_OFFSET: usize = 510;
+
+/// OS type of the partition which covers a GPT disk in its protective MBR
+pub const PROTECTIVE_OS_TYPE: u8 = 0xee;
+
+/// OS type of an EFI system partition
+pub const EFI_SYSTEM_OS_TYPE: u8 = 0xef;
+
+
+/// Partition table in the first block of a disk
+#[derive(Clone, Copy, Debug)]
+pub struct Mbr {
+    pub disk_signature: u32,
+    pub partitions: [MbrPartition; 4],
+}
+
+impl Mbr {
+
+    /// Reads the MBR from the start of a disk
+    ///
+    /// Fails with `Status::NotFound` if the MBR does not end with the boot signature.
+    pub fn read<R: Read + Seek>(disk: &mut R) -> Result<Mbr, Status> {
+
+        let mut buf = [0; MBR_SIZE];
+        disk.seek(SeekFrom::Start(0))?;
+        disk.read_exact(&mut buf)?;
+        Mbr::parse(&buf).ok_or(Status::NotFound)
+    }
+
+    /// Parses an MBR from the contents of a disk's first block
+    pub fn parse(block: &[u8]) -> Option<Mbr> {
+
+        if block.len() < MBR_SIZE || block[SIGNATURE_OFFSET..SIGNATURE_OFFSET + 2] != [0x55, 0xaa] {
+            return None;
+        }
+
+        let mut partitions = [MbrPartition::default(); 4];
+        for (index, partition) in partitions.iter_mut().enumerate() {
+            let offset = PARTITION_RECORDS_OFFSET + index * PARTITION_RECORD_SIZE;
+            *partition = MbrPartition::parse(&block[offset..offset + PARTITION_RECORD_SIZE])?;
+        }
+
+        Some(Mbr {
+            disk_signature: (&block[DISK_SIGNATURE_OFFSET..]).read_u32_le().ok()?,
+            partitions: partitions,
+        })
+    }
+
+    /// Tells whether this is a protective MBR, which indicates that the disk uses GPT
+    pub fn is_protective(&self) -> bool {
+
+        self.partitions.iter().any(|partition| partition.os_type == PROTECTIVE_OS_TYPE)
+    }
+
+    /// Returns an iterator over the partitions which are in use
+    pub fn used_partitions<'a>(&'a self) -> impl Iterator<Item = &'a MbrPartition> {
+
+        self.partitions.iter().filter(|partition| partition.is_used())
+    }
+}
+
+
+/// Partition record of an MBR
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MbrPartition {
+    pub boot_indicator: u8,
+    pub os_type: u8,
+    pub starting_lba: u32,
+    pub size_in_lba: u32,
+}
+
+impl MbrPartition {
+
+    /// Parses a 16-byte partition record
+    pub fn parse(record: &[u8]) -> Option<MbrPartition> {
+
+        if record.len() < PARTITION_RECORD_SIZE {
+            return None;
+        }
+
+        // The CHS addresses which follow the boot indicator and OS type are ignored
+        let mut lba_fields = &record[8..];
+        Some(MbrPartition {
+            boot_indicator: record[0],
+            os_type: record[4],
+            starting_lba: lba_fields.read_u32_le().ok()?,
+            size_in_lba: lba_fields.read_u32_le().ok()?,
+        })
+    }
+
+    /// Tells whether this record describes a partition
+    pub fn is_used(&self) -> bool {
+
+        self.os_type != 0 && self.size_in_lba != 0
+    }
+
+    /// Tells whether this is an EFI system partition
+    pub fn is_esp(&self) -> bool {
+
+        self.os_type == EFI_SYSTEM_OS_TYPE
+    }
+
+    /// Tells whether the partition is marked as bootable
+    pub fn is_bootable(&self) -> bool {
+
+        self.boot_indicator == 0x80
+    }
+
+    /// Returns the address of the partition's last block
+    pub fn ending_lba(&self) -> Lba {
+
+        (self.starting_lba as Lba + self.size_in_lba as Lba).saturating_sub(1)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use io::Cursor;
+    use super::*;
+
+    fn mbr(records: &[(u8, u8, u32, u32)]) -> [u8; MBR_SIZE] {
+
+        let mut block = [0; MBR_SIZE];
+        block[DISK_SIGNATURE_OFFSET..DISK_SIGNATURE_OFFSET + 4]
+            .copy_from_slice(&0x1234_5678u32.to_le_bytes());
+        for (index, record) in records.iter().enumerate() {
+            let offset = PARTITION_RECORDS_OFFSET + index * PARTITION_RECORD_SIZE;
+            let (boot_indicator, os_type, starting_lba, size_in_lba) = *record;
+            block[offset] = boot_indicator;
+            block[offset + 4] = os_type;
+            block[offset + 8..offset + 12].copy_from_slice(&starting_lba.to_le_bytes());
+            block[offset + 12..offset + 16].copy_from_slice(&size_in_lba.to_le_bytes());
+        }
+        block[SIGNATURE_OFFSET] = 0x55;
+        block[SIGNATURE_OFFSET + 1] = 0xaa;
+        block
+    }
+
+    #[test]
+    fn reads_protective_mbr() {
+
+        let block = mbr(&[(0, PROTECTIVE_OS_TYPE, 1, 0xffff_ffff)]);
+
+        let mbr = Mbr::read(&mut Cursor::new(&block[..])).unwrap();
+        assert!(mbr.is_protective());
+        assert_eq!(mbr.disk_signature, 0x1234_5678);
+        assert_eq!(mbr.used_partitions().count(), 1);
+        assert_eq!(mbr.partitions[0].starting_lba, 1);
+        assert_eq!(mbr.partitions[0].ending_lba(), 0xffff_ffff);
+    }
+
+    #[test]
+    fn reads_legacy_partitions() {
+
+        let block = mbr(&[
+            (0x80, EFI_SYSTEM_OS_TYPE, 2048, 1024),
+            (0, 0, 0, 0),
+            (0, 0x83, 4096, 8),
+        ]);
+
+        let mbr = Mbr::parse(&block).unwrap();
+        assert!(!mbr.is_protective());
+        let used: [MbrPartition; 2] = {
+            let mut used = mbr.used_partitions().cloned();
+            [used.next().unwrap(), used.next().unwrap()]
+        };
+        assert!(used[0].is_esp() && used[0].is_bootable());
+        assert_eq!(used[0].ending_lba(), 3071);
+        assert_eq!(used[1].os_type, 0x83);
+        assert!(!used[1].is_bootable());
+    }
+
+    #[test]
+    fn rejects_missing_signature() {
+
+        let mut block = mbr(&[(0, PROTECTIVE_OS_TYPE, 1, 0xffff_ffff)]);
+        block[SIGNATURE_OFFSET + 1] = 0;
+
+        assert!(Mbr::parse(&block).is_none());
+        let res = Mbr::read(&mut Cursor::new(&block[..]));
+        assert_eq!(res.err(), Some(Status::NotFound));
+    }
+}
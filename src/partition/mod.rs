@@ -0,0 +1,250 @@
+//! Parsing of MBR and GPT partition tables
+//!
+//! Partition tables are read from any stream which implements `io::Read` and `io::Seek`, such as
+//! a `BlockStream` or `DiskStream` over a disk. Substituting a `Cursor` over a disk image allows
+//! the same parsing code to be used outside of firmware.
+
+
+mod gpt;
+mod mbr;
+
+pub use self::gpt::*;
+pub use self::mbr::*;
+
+use {
+    boot_services::Guid,
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+    },
+    protocols::Lba,
+    types::Status,
+};
+
+
+/// Largest block size supported by the parsers
+pub const MAX_BLOCK_SIZE: usize = 4096;
+
+
+/// Reads a single block into the start of `buf`, returning the block
+fn read_block<'b, R: Read + Seek>(
+    disk: &mut R,
+    block_size: usize,
+    lba: Lba,
+    buf: &'b mut [u8; MAX_BLOCK_SIZE]
+) -> Result<&'b [u8], Status> {
+
+    if block_size < 512 || block_size > MAX_BLOCK_SIZE {
+        return Err(Status::Unsupported);
+    }
+
+    let offset = lba.checked_mul(block_size as u64).ok_or(Status::InvalidParameter)?;
+    disk.seek(SeekFrom::Start(offset))?;
+    disk.read_exact(&mut buf[..block_size])?;
+    Ok(&buf[..block_size])
+}
+
+
+/// Returns the address of the last block of a disk
+fn last_block<R: Seek>(disk: &mut R, block_size: usize) -> Result<Lba, Status> {
+
+    let len = disk.seek(SeekFrom::End(0))?;
+    match len / block_size as u64 {
+        0 => Err(Status::VolumeCorrupted),
+        blocks => Ok(blocks - 1),
+    }
+}
+
+
+/// Reads a Guid in its on-disk (mixed-endian) layout
+fn read_guid<R: Read>(reader: &mut R) -> Result<Guid, Status> {
+
+    let data_1 = reader.read_u32_le()?;
+    let data_2 = reader.read_u16_le()?;
+    let data_3 = reader.read_u16_le()?;
+    let mut data_4 = [0; 8];
+    reader.read_exact(&mut data_4)?;
+    Ok(Guid {
+        data_1: data_1,
+        data_2: data_2,
+        data_3: data_3,
+        data_4: data_4,
+    })
+}
+
+
+/// Incremental CRC32 as used by GPT (IEEE 802.3, reflected)
+struct Crc32(u32);
+
+impl Crc32 {
+
+    fn new() -> Crc32 {
+
+        Crc32(0xffff_ffff)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+
+        for byte in data {
+            self.0 ^= *byte as u32;
+            for _ in 0..8 {
+                let mask = (self.0 & 1).wrapping_neg();
+                self.0 = (self.0 >> 1) ^ (0xedb8_8320 & mask);
+            }
+        }
+    }
+
+    fn finish(&self) -> u32 {
+
+        !self.0
+    }
+}
+
+
+/// Type Guid of unused partition entries
+pub static UNUSED_PARTITION_GUID: Guid = Guid {
+    data_1: 0x00000000,
+    data_2: 0x0000,
+    data_3: 0x0000,
+    data_4: [
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+    ],
+};
+
+
+/// Type Guid of an EFI system partition
+pub static EFI_SYSTEM_PARTITION_GUID: Guid = Guid {
+    data_1: 0xc12a7328,
+    data_2: 0xf81f,
+    data_3: 0x11d2,
+    data_4: [
+        0xba,
+        0x4b,
+        0x00,
+        0xa0,
+        0xc9,
+        0x3e,
+        0xc9,
+        0x3b,
+    ],
+};
+
+
+/// Type Guid of a Microsoft basic data partition, which is also used for FAT and exFAT volumes
+pub static MICROSOFT_BASIC_DATA_GUID: Guid = Guid {
+    data_1: 0xebd0a0a2,
+    data_2: 0xb9e5,
+    data_3: 0x4433,
+    data_4: [
+        0x87,
+        0xc0,
+        0x68,
+        0xb6,
+        0xb7,
+        0x26,
+        0x99,
+        0xc7,
+    ],
+};
+
+
+/// Type Guid of a generic Linux filesystem partition
+pub static LINUX_FILESYSTEM_GUID: Guid = Guid {
+    data_1: 0x0fc63daf,
+    data_2: 0x8483,
+    data_3: 0x4772,
+    data_4: [
+        0x8e,
+        0x79,
+        0x3d,
+        0x69,
+        0xd8,
+        0x47,
+        0x7d,
+        0xe4,
+    ],
+};
+
+
+/// Type Guid of a Linux root partition on x86-64
+pub static LINUX_ROOT_X86_64_GUID: Guid = Guid {
+    data_1: 0x4f68bce3,
+    data_2: 0xe8cd,
+    data_3: 0x4db1,
+    data_4: [
+        0x96,
+        0xe7,
+        0xfb,
+        0xca,
+        0xf9,
+        0x84,
+        0xb7,
+        0x09,
+    ],
+};
+
+
+/// Type Guid of a Linux root partition on AArch64
+pub static LINUX_ROOT_AARCH64_GUID: Guid = Guid {
+    data_1: 0xb921b045,
+    data_2: 0x1df0,
+    data_3: 0x41c3,
+    data_4: [
+        0xaf,
+        0x44,
+        0x4c,
+        0x6f,
+        0x28,
+        0x0d,
+        0x3f,
+        0xae,
+    ],
+};
+
+
+/// Type Guid of a Linux swap partition
+pub static LINUX_SWAP_GUID: Guid = Guid {
+    data_1: 0x0657fd6d,
+    data_2: 0xa4ab,
+    data_3: 0x43c4,
+    data_4: [
+        0x84,
+        0xe5,
+        0x09,
+        0x33,
+        0xc8,
+        0x4b,
+        0x4f,
+        0x4f,
+    ],
+};
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn crc32_matches_check_values() {
+
+        let mut crc = Crc32::new();
+        assert_eq!(crc.finish(), 0);
+
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+
+        let mut crc = Crc32::new();
+        crc.update(b"The quick brown fox ");
+        crc.update(b"jumps over the lazy dog");
+        assert_eq!(crc.finish(), 0x414f_a339);
+    }
+}
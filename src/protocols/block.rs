@@ -0,0 +1,672 @@
+use core::{
+    cmp,
+    fmt,
+    ptr,
+};
+use {
+    boot_services::{
+        BootServices,
+        Event,
+        Guid,
+        Pool,
+        Protocol,
+    },
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+        offset_position,
+    },
+    types::{
+        Bool,
+        EfiBs,
+        Status,
+    },
+};
+
+
+/// Logical block address
+pub type Lba = u64;
+
+
+/// Provides raw access to the blocks of a mass storage device or partition
+#[repr(C)]
+pub struct BlockIO {
+    pub revision: u64,
+    pub media: EfiBs<BlockIOMedia>,
+    pub _reset: extern "win64" fn(this: &BlockIO, extended_verification: Bool) -> Status,
+    pub _read_blocks: extern "win64" fn(
+        this: &BlockIO,
+        media_id: u32,
+        lba: Lba,
+        buffer_size: usize,
+        buffer: *mut u8
+    ) -> Status,
+    pub _write_blocks: extern "win64" fn(
+        this: &BlockIO,
+        media_id: u32,
+        lba: Lba,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status,
+    pub _flush_blocks: extern "win64" fn(this: &BlockIO) -> Status,
+}
+
+impl BlockIO {
+
+    /// Resets the block device hardware
+    pub fn reset(&self, extended_verification: bool) -> Result<(), Status> {
+
+        (self._reset)(self, Bool::from(extended_verification))
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Reads blocks from the device, starting at the given LBA
+    ///
+    /// The length of `buf` must be a multiple of the media's block size, and `media_id` must match
+    /// the current media. Some devices also require `buf` to be aligned to the media's `io_align`.
+    pub fn read_blocks(&self, media_id: u32, lba: Lba, buf: &mut [u8]) -> Result<(), Status> {
+
+        (self._read_blocks)(self, media_id, lba, buf.len(), buf.as_mut_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Writes blocks to the device, starting at the given LBA
+    ///
+    /// The same requirements on `buf` apply as for `read_blocks`.
+    pub fn write_blocks(&self, media_id: u32, lba: Lba, buf: &[u8]) -> Result<(), Status> {
+
+        (self._write_blocks)(self, media_id, lba, buf.len(), buf.as_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Flushes all modified data to the physical device
+    pub fn flush_blocks(&self) -> Result<(), Status> {
+
+        (self._flush_blocks)(self)
+            .as_result()?;
+        Ok(())
+    }
+}
+
+impl Protocol for BlockIO {
+
+    fn guid() -> &'static Guid { &BLOCK_IO_GUID }
+}
+
+impl fmt::Debug for BlockIO {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BlockIO")
+            .field("revision", &self.revision)
+            .field("media", &self.media)
+            .finish()
+    }
+}
+
+
+/// Static Guid for BlockIO
+static BLOCK_IO_GUID: Guid = Guid {
+    data_1: 0x964e5b21,
+    data_2: 0x6459,
+    data_3: 0x11d2,
+    data_4: [
+        0x8e,
+        0x39,
+        0x00,
+        0xa0,
+        0xc9,
+        0x69,
+        0x72,
+        0x3b,
+    ],
+};
+
+
+/// Treats the blocks of a device as a stream of bytes with a current position
+///
+/// Each read or write transfers at most one block, going through an internal buffer, so neither
+/// the position nor the length of a transfer needs to be aligned. Partial blocks are written by
+/// reading the block, modifying it and writing it back.
+pub struct BlockStream<'a> {
+    block_io: &'a BlockIO,
+    buf: Pool<'a, [u8]>,
+    /// Offset of the block within `buf` which satisfies the media's `io_align`
+    block_offset: usize,
+    block_size: usize,
+    position: u64,
+}
+
+impl<'a> BlockStream<'a> {
+
+    /// Creates a stream over the current media of a block device
+    ///
+    /// Fails with `Status::InvalidParameter` if the media's `io_align` is not a power of two.
+    pub fn new(
+        block_io: &'a BlockIO,
+        boot_services: &'a BootServices
+    ) -> Result<BlockStream<'a>, Status> {
+
+        // An io_align of 0 or 1 means that buffers need no particular alignment
+        let align = cmp::max(block_io.media.io_align as usize, 1);
+        if !align.is_power_of_two() {
+            return Err(Status::InvalidParameter);
+        }
+
+        // Pool memory is only 8-byte aligned, so room is left to align the block within it
+        let block_size = block_io.media.block_size as usize;
+        let len = block_size.checked_add(align - 1).ok_or(Status::InvalidParameter)?;
+        let buf = boot_services.allocate_slice::<u8>(len)?;
+        let block_offset = (align - buf.as_ptr() as usize % align) % align;
+
+        Ok(BlockStream {
+            block_io: block_io,
+            buf: buf,
+            block_offset: block_offset,
+            block_size: block_size,
+            position: 0,
+        })
+    }
+
+    /// Returns the length of the media in bytes
+    pub fn len(&self) -> u64 {
+
+        (self.block_io.media.last_block + 1) * self.block_io.media.block_size as u64
+    }
+
+    /// Returns the block containing the current position, the offset of the position within it,
+    /// and the number of bytes of a transfer of `len` bytes which fall within that block
+    fn locate(&self, len: usize) -> (Lba, usize, usize) {
+
+        let block_size = self.block_size as u64;
+        let offset = (self.position % block_size) as usize;
+        let remaining = self.len().saturating_sub(self.position);
+        let len = cmp::min(cmp::min(len, self.block_size - offset) as u64, remaining) as usize;
+        (self.position / block_size, offset, len)
+    }
+
+    /// Returns the aligned buffer which holds a single block
+    fn block_buf(&mut self) -> &mut [u8] {
+
+        &mut self.buf[self.block_offset..self.block_offset + self.block_size]
+    }
+}
+
+impl<'a> Read for BlockStream<'a> {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        let (lba, offset, len) = self.locate(buf.len());
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let block_io = self.block_io;
+        let block = self.block_buf();
+        block_io.read_blocks(block_io.media.media_id, lba, block)?;
+        buf[..len].copy_from_slice(&block[offset..offset + len]);
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<'a> Write for BlockStream<'a> {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        let (lba, offset, len) = self.locate(buf.len());
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let block_io = self.block_io;
+        let media_id = block_io.media.media_id;
+        let block = self.block_buf();
+        if len < block.len() {
+            block_io.read_blocks(media_id, lba, block)?;
+        }
+        block[offset..offset + len].copy_from_slice(&buf[..len]);
+        block_io.write_blocks(media_id, lba, block)?;
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        self.block_io.flush_blocks()
+    }
+}
+
+impl<'a> Seek for BlockStream<'a> {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => offset_position(self.len(), offset)?,
+            SeekFrom::Current(offset) => offset_position(self.position, offset)?,
+        };
+        Ok(self.position)
+    }
+}
+
+
+/// Describes the media of a block device
+///
+/// The fields following `last_block` are only present if the protocol's revision is at least
+/// `BLOCK_IO_REVISION2` (and `optimal_transfer_length_granularity` requires
+/// `BLOCK_IO_REVISION3`).
+#[derive(Debug)]
+#[repr(C)]
+pub struct BlockIOMedia {
+    pub media_id: u32,
+    pub removable_media: Bool,
+    pub media_present: Bool,
+    pub logical_partition: Bool,
+    pub read_only: Bool,
+    pub write_caching: Bool,
+    pub block_size: u32,
+    pub io_align: u32,
+    pub last_block: Lba,
+    pub lowest_aligned_lba: Lba,
+    pub logical_blocks_per_physical_block: u32,
+    pub optimal_transfer_length_granularity: u32,
+}
+
+
+/// First revision of BlockIO which includes `lowest_aligned_lba` and
+/// `logical_blocks_per_physical_block`
+pub const BLOCK_IO_REVISION2: u64 = 0x0002_0001;
+
+/// First revision of BlockIO which includes `optimal_transfer_length_granularity`
+pub const BLOCK_IO_REVISION3: u64 = 0x0002_001f;
+
+
+/// Provides asynchronous access to the blocks of a mass storage device or partition
+#[repr(C)]
+pub struct BlockIO2 {
+    pub media: EfiBs<BlockIOMedia>,
+    pub _reset: extern "win64" fn(this: &BlockIO2, extended_verification: Bool) -> Status,
+    pub _read_blocks_ex: extern "win64" fn(
+        this: &BlockIO2,
+        media_id: u32,
+        lba: Lba,
+        token: *mut BlockIO2Token,
+        buffer_size: usize,
+        buffer: *mut u8
+    ) -> Status,
+    pub _write_blocks_ex: extern "win64" fn(
+        this: &BlockIO2,
+        media_id: u32,
+        lba: Lba,
+        token: *mut BlockIO2Token,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status,
+    pub _flush_blocks_ex: extern "win64" fn(this: &BlockIO2, token: *mut BlockIO2Token) -> Status,
+}
+
+impl BlockIO2 {
+
+    /// Resets the block device hardware
+    pub fn reset(&self, extended_verification: bool) -> Result<(), Status> {
+
+        (self._reset)(self, Bool::from(extended_verification))
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Reads blocks from the device, waiting for the read to complete
+    pub fn read_blocks(&self, media_id: u32, lba: Lba, buf: &mut [u8]) -> Result<(), Status> {
+
+        (self._read_blocks_ex)(self, media_id, lba, ptr::null_mut(), buf.len(), buf.as_mut_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Writes blocks to the device, waiting for the write to complete
+    pub fn write_blocks(&self, media_id: u32, lba: Lba, buf: &[u8]) -> Result<(), Status> {
+
+        (self._write_blocks_ex)(self, media_id, lba, ptr::null_mut(), buf.len(), buf.as_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Flushes all modified data to the physical device, waiting for the flush to complete
+    pub fn flush_blocks(&self) -> Result<(), Status> {
+
+        (self._flush_blocks_ex)(self, ptr::null_mut())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Starts reading blocks from the device
+    ///
+    /// The token's event is signaled once the read completes, after which the token's
+    /// `transaction_status` holds the result.
+    ///
+    /// # Safety
+    ///
+    /// `token` and `buf` must not be moved, dropped or otherwise accessed until the token's event
+    /// has been signaled, because the device writes to them in the background.
+    pub unsafe fn read_blocks_ex(
+        &self,
+        media_id: u32,
+        lba: Lba,
+        token: &mut BlockIO2Token,
+        buf: &mut [u8]
+    ) -> Result<(), Status> {
+
+        (self._read_blocks_ex)(self, media_id, lba, token, buf.len(), buf.as_mut_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Starts writing blocks to the device
+    ///
+    /// The token's event is signaled once the write completes, after which the token's
+    /// `transaction_status` holds the result.
+    ///
+    /// # Safety
+    ///
+    /// `token` must not be moved, dropped or otherwise accessed, and `buf` must not be modified,
+    /// until the token's event has been signaled.
+    pub unsafe fn write_blocks_ex(
+        &self,
+        media_id: u32,
+        lba: Lba,
+        token: &mut BlockIO2Token,
+        buf: &[u8]
+    ) -> Result<(), Status> {
+
+        (self._write_blocks_ex)(self, media_id, lba, token, buf.len(), buf.as_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Starts flushing all modified data to the physical device
+    ///
+    /// # Safety
+    ///
+    /// `token` must not be moved, dropped or otherwise accessed until its event has been signaled.
+    pub unsafe fn flush_blocks_ex(&self, token: &mut BlockIO2Token) -> Result<(), Status> {
+
+        (self._flush_blocks_ex)(self, token)
+            .as_result()?;
+        Ok(())
+    }
+}
+
+impl Protocol for BlockIO2 {
+
+    fn guid() -> &'static Guid { &BLOCK_IO2_GUID }
+}
+
+impl fmt::Debug for BlockIO2 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("BlockIO2")
+            .field("media", &self.media)
+            .finish()
+    }
+}
+
+
+/// Static Guid for BlockIO2
+static BLOCK_IO2_GUID: Guid = Guid {
+    data_1: 0xa77b2472,
+    data_2: 0xe282,
+    data_3: 0x4e9f,
+    data_4: [
+        0xa2,
+        0x45,
+        0xc2,
+        0xc0,
+        0xe2,
+        0x7b,
+        0xbc,
+        0xc1,
+    ],
+};
+
+
+/// Tracks an asynchronous BlockIO2 request
+#[derive(Debug)]
+#[repr(C)]
+pub struct BlockIO2Token<'a> {
+    pub event: &'a Event,
+    pub transaction_status: Status,
+}
+
+impl<'a> BlockIO2Token<'a> {
+
+    /// Creates a token which signals the given event upon completion
+    pub fn new(event: &'a Event) -> BlockIO2Token<'a> {
+
+        BlockIO2Token {
+            event: event,
+            transaction_status: Status::Success,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use core::{
+        cell::RefCell,
+        mem,
+        slice,
+    };
+    use std::{
+        boxed::Box,
+        vec::Vec,
+    };
+    use boot_services::testing;
+    use super::*;
+
+    const BLOCK_SIZE: usize = 512;
+    const NUM_BLOCKS: usize = 4;
+    const MEDIA_ID: u32 = 7;
+
+    /// BlockIO backed by memory, which rejects misaligned buffers
+    #[repr(C)]
+    struct MemoryBlockIO {
+        block_io: BlockIO,
+        data: RefCell<Vec<u8>>,
+    }
+
+    impl MemoryBlockIO {
+
+        fn new(io_align: u32) -> &'static MemoryBlockIO {
+
+            let media = Box::leak(Box::new(BlockIOMedia {
+                media_id: MEDIA_ID,
+                removable_media: Bool::False,
+                media_present: Bool::True,
+                logical_partition: Bool::False,
+                read_only: Bool::False,
+                write_caching: Bool::False,
+                block_size: BLOCK_SIZE as u32,
+                io_align: io_align,
+                last_block: NUM_BLOCKS as Lba - 1,
+                lowest_aligned_lba: 0,
+                logical_blocks_per_physical_block: 1,
+                optimal_transfer_length_granularity: 0,
+            }));
+            let data = (0..BLOCK_SIZE * NUM_BLOCKS).map(|i| (i % 251) as u8).collect();
+
+            Box::leak(Box::new(MemoryBlockIO {
+                block_io: BlockIO {
+                    revision: BLOCK_IO_REVISION3,
+                    media: unsafe { mem::transmute::<&BlockIOMedia, EfiBs<BlockIOMedia>>(media) },
+                    _reset: reset,
+                    _read_blocks: read_blocks,
+                    _write_blocks: write_blocks,
+                    _flush_blocks: flush_blocks,
+                },
+                data: RefCell::new(data),
+            }))
+        }
+
+        /// Returns the bytes covered by a transfer, if it is valid
+        fn range(
+            this: &BlockIO,
+            media_id: u32,
+            lba: Lba,
+            buffer_size: usize,
+            buffer: usize
+        ) -> Result<(&MemoryBlockIO, usize, usize), Status> {
+
+            let align = cmp::max(this.media.io_align as usize, 1);
+            if media_id != MEDIA_ID {
+                return Err(Status::MediaChanged);
+            }
+            if buffer % align != 0 || buffer_size % BLOCK_SIZE != 0 {
+                return Err(Status::InvalidParameter);
+            }
+
+            let start = lba as usize * BLOCK_SIZE;
+            if start + buffer_size > BLOCK_SIZE * NUM_BLOCKS {
+                return Err(Status::InvalidParameter);
+            }
+
+            let this = unsafe { &*(this as *const BlockIO as *const MemoryBlockIO) };
+            Ok((this, start, start + buffer_size))
+        }
+    }
+
+    extern "win64" fn reset(_: &BlockIO, _: Bool) -> Status {
+
+        Status::Success
+    }
+
+    extern "win64" fn read_blocks(
+        this: &BlockIO,
+        media_id: u32,
+        lba: Lba,
+        buffer_size: usize,
+        buffer: *mut u8
+    ) -> Status {
+
+        match MemoryBlockIO::range(this, media_id, lba, buffer_size, buffer as usize) {
+            Ok((this, start, end)) => {
+                let buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_size) };
+                buf.copy_from_slice(&this.data.borrow()[start..end]);
+                Status::Success
+            },
+            Err(err) => err,
+        }
+    }
+
+    extern "win64" fn write_blocks(
+        this: &BlockIO,
+        media_id: u32,
+        lba: Lba,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status {
+
+        match MemoryBlockIO::range(this, media_id, lba, buffer_size, buffer as usize) {
+            Ok((this, start, end)) => {
+                let buf = unsafe { slice::from_raw_parts(buffer, buffer_size) };
+                this.data.borrow_mut()[start..end].copy_from_slice(buf);
+                Status::Success
+            },
+            Err(err) => err,
+        }
+    }
+
+    extern "win64" fn flush_blocks(_: &BlockIO) -> Status {
+
+        Status::Success
+    }
+
+    #[test]
+    fn reads_across_block_boundary() {
+
+        let disk = MemoryBlockIO::new(0);
+        let mut stream = BlockStream::new(&disk.block_io, testing::boot_services()).unwrap();
+
+        let start = BLOCK_SIZE as u64 - 2;
+        assert_eq!(stream.seek(SeekFrom::Start(start)), Ok(start));
+        let mut buf = [0; 4];
+        // A single read stops at the end of the block
+        assert_eq!(stream.read(&mut buf), Ok(2));
+        assert_eq!(stream.read(&mut buf[2..]), Ok(2));
+        assert_eq!(&buf[..], &disk.data.borrow()[BLOCK_SIZE - 2..BLOCK_SIZE + 2]);
+        assert_eq!(stream.stream_position(), Ok(start + 4));
+    }
+
+    #[test]
+    fn writes_partial_blocks() {
+
+        let disk = MemoryBlockIO::new(0);
+        let mut stream = BlockStream::new(&disk.block_io, testing::boot_services()).unwrap();
+        let before = disk.data.borrow().clone();
+
+        stream.seek(SeekFrom::Start(BLOCK_SIZE as u64 * 2 - 3)).unwrap();
+        stream.write_all(&[0xaa; 6]).unwrap();
+
+        let after = disk.data.borrow();
+        assert_eq!(&after[BLOCK_SIZE * 2 - 3..BLOCK_SIZE * 2 + 3], &[0xaa; 6]);
+        assert_eq!(&after[..BLOCK_SIZE * 2 - 3], &before[..BLOCK_SIZE * 2 - 3]);
+        assert_eq!(&after[BLOCK_SIZE * 2 + 3..], &before[BLOCK_SIZE * 2 + 3..]);
+    }
+
+    #[test]
+    fn seeks_relative_to_end_and_current_position() {
+
+        let disk = MemoryBlockIO::new(0);
+        let mut stream = BlockStream::new(&disk.block_io, testing::boot_services()).unwrap();
+        let len = (BLOCK_SIZE * NUM_BLOCKS) as u64;
+        assert_eq!(stream.len(), len);
+
+        assert_eq!(stream.seek(SeekFrom::Current(-1)), Err(Status::InvalidParameter));
+        assert_eq!(stream.seek(SeekFrom::End(-(len as i64) - 1)), Err(Status::InvalidParameter));
+        assert_eq!(stream.stream_position(), Ok(0));
+
+        assert_eq!(stream.seek(SeekFrom::End(-4)), Ok(len - 4));
+        assert_eq!(stream.seek(SeekFrom::Current(-4)), Ok(len - 8));
+        let mut buf = [0; 16];
+        assert_eq!(stream.read(&mut buf), Ok(8));
+        assert_eq!(&buf[..8], &disk.data.borrow()[len as usize - 8..]);
+    }
+
+    #[test]
+    fn stops_at_end_of_media() {
+
+        let disk = MemoryBlockIO::new(0);
+        let mut stream = BlockStream::new(&disk.block_io, testing::boot_services()).unwrap();
+        let len = stream.len();
+
+        // Seeking past the end is allowed, but nothing can be transferred there
+        assert_eq!(stream.seek(SeekFrom::End(10)), Ok(len + 10));
+        let mut buf = [0; 4];
+        assert_eq!(stream.read(&mut buf), Ok(0));
+        assert_eq!(stream.read_exact(&mut buf), Err(Status::EndOfFile));
+        assert_eq!(stream.write(&buf), Ok(0));
+        assert_eq!(stream.write_all(&buf), Err(Status::VolumeFull));
+    }
+
+    #[test]
+    fn aligns_block_buffer() {
+
+        for io_align in &[2, 64, 4096] {
+            let disk = MemoryBlockIO::new(*io_align);
+            let mut stream = BlockStream::new(&disk.block_io, testing::boot_services()).unwrap();
+
+            let mut buf = [0; 8];
+            stream.seek(SeekFrom::Start(BLOCK_SIZE as u64)).unwrap();
+            assert_eq!(stream.read(&mut buf), Ok(8));
+            assert_eq!(stream.write(&buf), Ok(8));
+        }
+
+        let disk = MemoryBlockIO::new(48);
+        let res = BlockStream::new(&disk.block_io, testing::boot_services());
+        assert_eq!(res.err(), Some(Status::InvalidParameter));
+    }
+}
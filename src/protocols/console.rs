@@ -1,24 +1,33 @@
-use core::fmt;
+use core::{
+    char::REPLACEMENT_CHARACTER,
+    cmp,
+    fmt,
+    mem,
+    ptr,
+    time::Duration,
+};
 use {
     boot_services::{
+        critical_section,
+        drop_closure,
+        BootServices,
         Event,
         Guid,
         Protocol,
+        Timer,
+        POOL_ALIGNMENT,
     },
     types::{
         Bool,
         Char16,
         EfiRt,
+        MemoryType,
         Status,
     },
 };
 
 
 /// This protocol is used to obtain input from the ConsoleIn device
-///
-/// TODO:
-///
-/// * implement EFI events so that wait_for_key can be used
 #[repr(C)]
 pub struct SimpleTextInput {
     pub _reset: extern "win64" fn(this: &SimpleTextInput, extended_verification: Bool) -> Status,
@@ -47,6 +56,44 @@ impl SimpleTextInput {
             .as_result()
             .map(|_| key)
     }
+
+    /// Waits until a key is pressed, then returns it
+    pub fn read_key_blocking(&self, boot_services: &BootServices) -> Result<InputKey, Status> {
+
+        loop {
+            boot_services.wait_for_event(&[&*self.wait_for_key])?;
+
+            // Another consumer of this device may have read the key first
+            match self.read_key_stroke() {
+                Err(Status::NotReady) => continue,
+                res => return res,
+            }
+        }
+    }
+
+    /// Waits until a key is pressed or the timeout expires
+    ///
+    /// Returns `Status::Timeout` if no key is pressed before the timeout expires.
+    pub fn read_key_timeout(
+        &self,
+        boot_services: &BootServices,
+        timeout: Duration
+    ) -> Result<InputKey, Status> {
+
+        // The timer is armed once, so that waking without a key does not extend the timeout
+        let timer = Timer::one_shot(boot_services, timeout)?;
+
+        loop {
+            match boot_services.wait_for_event(&[&*self.wait_for_key, &timer])? {
+                0 => match self.read_key_stroke() {
+                    // Another reader took the key first, so wait for the next one
+                    Err(Status::NotReady) => continue,
+                    res => return res,
+                },
+                _ => return Err(Status::Timeout),
+            }
+        }
+    }
 }
 
 impl Protocol for SimpleTextInput {
@@ -83,6 +130,7 @@ static SIMPLE_TEXT_INPUT_GUID: Guid = Guid {
 
 /// Describes a keystroke
 #[derive(Clone, Copy, Debug)]
+#[repr(C)]
 pub struct InputKey {
     pub scan_code: ScanCode,
     pub unicode_char: Char16,
@@ -90,33 +138,445 @@ pub struct InputKey {
 
 
 /// Represents special keys
+///
+/// Firmware may report scan codes which are not listed here (e.g. vendor-specific keys), so this
+/// is an open-ended set of values rather than an enum.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct ScanCode(pub u16);
+
+#[allow(non_upper_case_globals)]
+impl ScanCode {
+    pub const Null: ScanCode = ScanCode(0x00);
+    pub const CursorUp: ScanCode = ScanCode(0x01);
+    pub const CursorDown: ScanCode = ScanCode(0x02);
+    pub const CursorRight: ScanCode = ScanCode(0x03);
+    pub const CursorLeft: ScanCode = ScanCode(0x04);
+    pub const Home: ScanCode = ScanCode(0x05);
+    pub const End: ScanCode = ScanCode(0x06);
+    pub const Insert: ScanCode = ScanCode(0x07);
+    pub const Delete: ScanCode = ScanCode(0x08);
+    pub const PageUp: ScanCode = ScanCode(0x09);
+    pub const PageDown: ScanCode = ScanCode(0x0a);
+    pub const Function1: ScanCode = ScanCode(0x0b);
+    pub const Function2: ScanCode = ScanCode(0x0c);
+    pub const Function3: ScanCode = ScanCode(0x0d);
+    pub const Function4: ScanCode = ScanCode(0x0e);
+    pub const Function5: ScanCode = ScanCode(0x0f);
+    pub const Function6: ScanCode = ScanCode(0x10);
+    pub const Function7: ScanCode = ScanCode(0x11);
+    pub const Function8: ScanCode = ScanCode(0x12);
+    pub const Function9: ScanCode = ScanCode(0x13);
+    pub const Function10: ScanCode = ScanCode(0x14);
+    pub const Function11: ScanCode = ScanCode(0x15);
+    pub const Function12: ScanCode = ScanCode(0x16);
+    pub const Escape: ScanCode = ScanCode(0x17);
+    pub const Function13: ScanCode = ScanCode(0x68);
+    pub const Function14: ScanCode = ScanCode(0x69);
+    pub const Function15: ScanCode = ScanCode(0x6a);
+    pub const Function16: ScanCode = ScanCode(0x6b);
+    pub const Function17: ScanCode = ScanCode(0x6c);
+    pub const Function18: ScanCode = ScanCode(0x6d);
+    pub const Function19: ScanCode = ScanCode(0x6e);
+    pub const Function20: ScanCode = ScanCode(0x6f);
+    pub const Function21: ScanCode = ScanCode(0x70);
+    pub const Function22: ScanCode = ScanCode(0x71);
+    pub const Function23: ScanCode = ScanCode(0x72);
+    pub const Function24: ScanCode = ScanCode(0x73);
+    pub const Mute: ScanCode = ScanCode(0x7f);
+    pub const VolumeUp: ScanCode = ScanCode(0x80);
+    pub const VolumeDown: ScanCode = ScanCode(0x81);
+    pub const BrightnessUp: ScanCode = ScanCode(0x100);
+    pub const BrightnessDown: ScanCode = ScanCode(0x101);
+    pub const Suspend: ScanCode = ScanCode(0x102);
+    pub const Hibernate: ScanCode = ScanCode(0x103);
+    pub const ToggleDisplay: ScanCode = ScanCode(0x104);
+    pub const Recovery: ScanCode = ScanCode(0x105);
+    pub const Eject: ScanCode = ScanCode(0x106);
+}
+
+
+/// Extended version of SimpleTextInput which reports shift and toggle key state
+#[repr(C)]
+pub struct SimpleTextInputEx {
+    pub _reset: extern "win64" fn(this: &SimpleTextInputEx, extended_verification: Bool) -> Status,
+    pub _read_key_stroke_ex: extern "win64" fn(
+        this: &SimpleTextInputEx,
+        key_data: &mut KeyData
+    ) -> Status,
+    pub wait_for_key_ex: EfiRt<Event>,
+    pub _set_state: extern "win64" fn(
+        this: &SimpleTextInputEx,
+        key_toggle_state: &KeyToggleState
+    ) -> Status,
+    pub _register_key_notify: extern "win64" fn(
+        this: &SimpleTextInputEx,
+        key_data: &KeyData,
+        key_notification_function: extern "win64" fn(key_data: &KeyData) -> Status,
+        notify_handle: &mut KeyNotifyHandle
+    ) -> Status,
+    pub _unregister_key_notify: extern "win64" fn(
+        this: &SimpleTextInputEx,
+        notification_handle: KeyNotifyHandle
+    ) -> Status,
+}
+
+impl SimpleTextInputEx {
+
+    /// Reset the input device and optionally run diagnostics
+    pub fn reset(&self, extended_verification: bool) -> Result<(), Status> {
+
+        (self._reset)(self, Bool::from(extended_verification))
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Returns the next input character along with the current shift and toggle state
+    pub fn read_key_stroke_ex(&self) -> Result<KeyData, Status> {
+
+        let mut key_data = KeyData {
+            key: InputKey {
+                scan_code: ScanCode::Null,
+                unicode_char: 0,
+            },
+            key_state: KeyState {
+                key_shift_state: KeyShiftState::empty(),
+                key_toggle_state: KeyToggleState::empty(),
+            },
+        };
+        (self._read_key_stroke_ex)(self, &mut key_data)
+            .as_result()
+            .map(|_| key_data)
+    }
+
+    /// Sets certain state for the input device, such as the state of the toggle keys
+    pub fn set_state(&self, key_toggle_state: KeyToggleState) -> Result<(), Status> {
+
+        (self._set_state)(self, &key_toggle_state)
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Registers a closure which is called when the specified keystroke is pressed
+    ///
+    /// The notification is unregistered when the returned `KeyNotification` is dropped. The
+    /// closure must be `'static` because leaking the notification with `mem::forget` leaves the
+    /// firmware able to call it indefinitely.
+    ///
+    /// Since the firmware passes no context to key notification functions, each registration
+    /// occupies one of a fixed number of slots. Fails with `Status::OutOfResources` if every slot
+    /// is in use, and with `Status::Unsupported` if the closure requires a greater alignment than
+    /// pool memory provides.
+    pub fn register_key_notify<'a, F>(&'a self,
+                                      boot_services: &'a BootServices,
+                                      key_data: &KeyData,
+                                      callback: F)
+        -> Result<KeyNotification<'a>, Status> where F: FnMut(&KeyData) + 'static {
+
+        if mem::align_of::<F>() > POOL_ALIGNMENT {
+            return Err(Status::Unsupported);
+        }
+
+        // Move the closure into pool memory so that it has a stable address
+        let size = cmp::max(mem::size_of::<F>(), 1);
+        let context = boot_services.allocate_pool(MemoryType::LoaderData, size)?;
+        unsafe { ptr::write(context as *mut F, callback) };
+
+        let slot = critical_section(boot_services, || unsafe {
+            let slot = (0..KEY_NOTIFY_SLOT_COUNT)
+                .find(|&slot| KEY_NOTIFY_SLOTS[slot].call.is_none())?;
+            KEY_NOTIFY_SLOTS[slot] = KeyNotifySlot {
+                callback: context,
+                call: Some(call_closure::<F>),
+            };
+            Some(slot)
+        });
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                unsafe { drop_closure::<F>(context) };
+                let _ = boot_services.free_pool(context);
+                return Err(Status::OutOfResources);
+            },
+        };
+
+        let mut handle = KeyNotifyHandle(0 as *const ());
+        let res = (self._register_key_notify)(
+            self,
+            key_data,
+            KEY_NOTIFY_FUNCTIONS[slot],
+            &mut handle
+        )
+            .as_result();
+
+        match res {
+            Ok(_) => Ok(KeyNotification {
+                input: self,
+                boot_services: boot_services,
+                handle: handle,
+                slot: slot,
+                drop_callback: drop_closure::<F>,
+            }),
+            Err(err) => {
+                release_slot(boot_services, slot);
+                unsafe { drop_closure::<F>(context) };
+
+                // The registration error is more useful than any error from freeing the closure
+                let _ = boot_services.free_pool(context);
+                Err(err)
+            },
+        }
+    }
+}
+
+impl Protocol for SimpleTextInputEx {
+
+    fn guid() -> &'static Guid { &SIMPLE_TEXT_INPUT_EX_GUID }
+}
+
+impl fmt::Debug for SimpleTextInputEx {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("SimpleTextInputEx")
+            .field("wait_for_key_ex", &self.wait_for_key_ex)
+            .finish()
+    }
+}
+
+
+/// Static Guid for SimpleTextInputEx
+static SIMPLE_TEXT_INPUT_EX_GUID: Guid = Guid {
+    data_1: 0xdd9e7534,
+    data_2: 0x7762,
+    data_3: 0x4698,
+    data_4: [
+        0x8c,
+        0x14,
+        0xf5,
+        0x85,
+        0x17,
+        0xa6,
+        0x25,
+        0xaa,
+    ],
+};
+
+
+/// Describes a keystroke along with the state of the shift and toggle keys
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct KeyData {
+    pub key: InputKey,
+    pub key_state: KeyState,
+}
+
+
+/// State of the shift and toggle keys at the time of a keystroke
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct KeyState {
+    pub key_shift_state: KeyShiftState,
+    pub key_toggle_state: KeyToggleState,
+}
+
+
+bitflags! {
+    /// Shift modifiers which were held during a keystroke
+    ///
+    /// None of the other bits are meaningful unless `SHIFT_STATE_VALID` is set.
+    pub struct KeyShiftState: u32 {
+        const SHIFT_STATE_VALID = 0x8000_0000;
+        const RIGHT_SHIFT_PRESSED = 0x0000_0001;
+        const LEFT_SHIFT_PRESSED = 0x0000_0002;
+        const RIGHT_CONTROL_PRESSED = 0x0000_0004;
+        const LEFT_CONTROL_PRESSED = 0x0000_0008;
+        const RIGHT_ALT_PRESSED = 0x0000_0010;
+        const LEFT_ALT_PRESSED = 0x0000_0020;
+        const RIGHT_LOGO_PRESSED = 0x0000_0040;
+        const LEFT_LOGO_PRESSED = 0x0000_0080;
+        const MENU_KEY_PRESSED = 0x0000_0100;
+        const SYS_REQ_PRESSED = 0x0000_0200;
+    }
+}
+
+impl KeyShiftState {
+
+    /// Tells whether either Shift key was held
+    pub fn shift(&self) -> bool {
+
+        self.intersects(KeyShiftState::LEFT_SHIFT_PRESSED | KeyShiftState::RIGHT_SHIFT_PRESSED)
+    }
+
+    /// Tells whether either Control key was held
+    pub fn control(&self) -> bool {
+
+        self.intersects(KeyShiftState::LEFT_CONTROL_PRESSED | KeyShiftState::RIGHT_CONTROL_PRESSED)
+    }
+
+    /// Tells whether either Alt key was held
+    pub fn alt(&self) -> bool {
+
+        self.intersects(KeyShiftState::LEFT_ALT_PRESSED | KeyShiftState::RIGHT_ALT_PRESSED)
+    }
+}
+
+
+bitflags! {
+    /// State of the toggle keys
+    ///
+    /// None of the other bits are meaningful unless `TOGGLE_STATE_VALID` is set.
+    pub struct KeyToggleState: u8 {
+        const TOGGLE_STATE_VALID = 0x80;
+        const KEY_STATE_EXPOSED = 0x40;
+        const SCROLL_LOCK_ACTIVE = 0x01;
+        const NUM_LOCK_ACTIVE = 0x02;
+        const CAPS_LOCK_ACTIVE = 0x04;
+    }
+}
+
+
+/// Identifies a keystroke notification registered with `register_key_notify`
 #[derive(Clone, Copy, Debug)]
-#[repr(u16)]
-pub enum ScanCode {
-    Null = 0x00,
-    CursorUp = 0x01,
-    CursorDown = 0x02,
-    CursorRight = 0x03,
-    CursorLeft = 0x04,
-    Home = 0x05,
-    End = 0x06,
-    Insert = 0x07,
-    Delete = 0x08,
-    PageUp = 0x09,
-    PageDown = 0x0a,
-    Function1 = 0x0b,
-    Function2 = 0x0c,
-    Function3 = 0x0d,
-    Function4 = 0x0e,
-    Function5 = 0x0f,
-    Function6 = 0x10,
-    Function7 = 0x11,
-    Function8 = 0x12,
-    Function9 = 0x13,
-    Function10 = 0x14,
-    Function11 = 0x15,
-    Function12 = 0x16,
-    Escape = 0x17,
+#[repr(C)]
+pub struct KeyNotifyHandle(*const ());
+
+
+/// A keystroke notification which is unregistered when dropped
+///
+/// The closure is dropped and freed once the notification has been unregistered. If
+/// unregistering fails, the closure and its slot are leaked rather than freed, since the firmware
+/// may still call it.
+pub struct KeyNotification<'a> {
+    input: &'a SimpleTextInputEx,
+    boot_services: &'a BootServices,
+    handle: KeyNotifyHandle,
+    slot: usize,
+    drop_callback: unsafe fn(*mut u8),
+}
+
+impl<'a> KeyNotification<'a> {
+
+    /// Unregisters the notification, reporting any error
+    ///
+    /// Dropping the notification also unregisters it, but ignores errors.
+    pub fn unregister(self) -> Result<(), Status> {
+
+        let res = self.unregister_and_free();
+        mem::forget(self);
+        res
+    }
+
+    /// Unregisters the notification, then drops and frees the closure if that succeeded
+    ///
+    /// This must only be called once.
+    fn unregister_and_free(&self) -> Result<(), Status> {
+
+        (self.input._unregister_key_notify)(self.input, self.handle)
+            .as_result()?;
+
+        let callback = unsafe { KEY_NOTIFY_SLOTS[self.slot].callback };
+        release_slot(self.boot_services, self.slot);
+        unsafe { (self.drop_callback)(callback) };
+        self.boot_services.free_pool(callback)
+    }
+}
+
+impl<'a> Drop for KeyNotification<'a> {
+
+    fn drop(&mut self) {
+
+        let _ = self.unregister_and_free();
+    }
+}
+
+impl<'a> fmt::Debug for KeyNotification<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("KeyNotification")
+            .field("handle", &self.handle)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
+
+/// A registered closure, along with the function which calls it
+#[derive(Clone, Copy)]
+struct KeyNotifySlot {
+    callback: *mut u8,
+    call: Option<unsafe fn(*mut u8, &KeyData)>,
+}
+
+/// Number of key notifications which may be registered at once
+const KEY_NOTIFY_SLOT_COUNT: usize = 8;
+
+/// Registered closures, indexed by slot
+///
+/// Slots are only modified inside a critical section, so notification functions never see a
+/// partially written slot.
+static mut KEY_NOTIFY_SLOTS: [KeyNotifySlot; KEY_NOTIFY_SLOT_COUNT] = [
+    KeyNotifySlot { callback: 0 as *mut u8, call: None };
+    KEY_NOTIFY_SLOT_COUNT
+];
+
+
+/// Defines a notification function for each slot, since the firmware passes no context
+macro_rules! key_notify_functions {
+    ($($name:ident = $slot:expr,)*) => {
+        $(
+            extern "win64" fn $name(key_data: &KeyData) -> Status {
+
+                call_slot($slot, key_data);
+                Status::Success
+            }
+        )*
+
+        /// Notification functions, indexed by slot
+        static KEY_NOTIFY_FUNCTIONS: [
+            extern "win64" fn(&KeyData) -> Status;
+            KEY_NOTIFY_SLOT_COUNT
+        ] = [$($name),*];
+    }
+}
+
+key_notify_functions! {
+    key_notify_0 = 0,
+    key_notify_1 = 1,
+    key_notify_2 = 2,
+    key_notify_3 = 3,
+    key_notify_4 = 4,
+    key_notify_5 = 5,
+    key_notify_6 = 6,
+    key_notify_7 = 7,
+}
+
+
+/// Calls the closure registered in a slot, if any
+fn call_slot(slot: usize, key_data: &KeyData) {
+
+    // Notification functions run above the level at which slots are modified
+    let slot = unsafe { KEY_NOTIFY_SLOTS[slot] };
+    if let Some(call) = slot.call {
+        unsafe { call(slot.callback, key_data) };
+    }
+}
+
+
+/// Calls a closure stored in pool memory
+unsafe fn call_closure<F>(callback: *mut u8, key_data: &KeyData)
+where F: FnMut(&KeyData) {
+
+    // The firmware never calls a notification function reentrantly, so there is only ever one
+    // mutable reference to the closure
+    let callback = &mut *(callback as *mut F);
+    callback(key_data);
+}
+
+
+/// Marks a slot as free
+fn release_slot(boot_services: &BootServices, slot: usize) {
+
+    critical_section(boot_services, || unsafe {
+        KEY_NOTIFY_SLOTS[slot] = KeyNotifySlot { callback: 0 as *mut u8, call: None };
+    });
 }
 
 
@@ -155,13 +615,16 @@ impl SimpleTextOutput {
     }
 
     /// Displays the string on the device at the current cursor location
-    pub fn output_string(&self, string: &str) -> Result<(), Status> {
+    ///
+    /// A successful result may still carry a warning such as `WarnUnknownGlyph`, indicating that
+    /// some characters could not be rendered by the device.
+    pub fn output_string(&self, string: &str) -> Result<Status, Status> {
 
         exec_with_str(string, |buf| (self._output_string)(self, buf))
     }
 
     /// Tests to see if the ConsoleOut device supports this string
-    pub fn test_string(&self, string: &str) -> Result<(), Status> {
+    pub fn test_string(&self, string: &str) -> Result<Status, Status> {
 
         exec_with_str(string, |buf| (self._test_string)(self, buf))
     }
@@ -183,21 +646,48 @@ impl SimpleTextOutput {
         Ok(())
     }
 
-    /// Sets the foreground and background color of the text that is output
-    pub fn set_attribute(&self, foreground: Color, background: Color) -> Result<(), Status> {
+    /// Returns an iterator over the modes supported by the output device
+    ///
+    /// Mode numbers which the device reports as unsupported are skipped.
+    pub fn modes<'a>(&'a self) -> TextModes<'a> {
 
-        // TODO: is this necessary, or will the implementation correctly report an error?
-        if !background.is_background() {
-            return Err(Status::InvalidParameter);
+        TextModes {
+            output: self,
+            next: 0,
         }
+    }
 
-        let attribute = ((background as usize) << 4) | (foreground as usize);
+    /// Returns the supported mode with the largest number of characters
+    pub fn best_mode(&self) -> Option<(usize, ModeDescriptor)> {
 
-        (self._set_attribute)(self, attribute)
+        self.modes()
+            .max_by_key(|&(_, desc)| desc.columns * desc.rows)
+    }
+
+    /// Sets the foreground and background color of the text that is output
+    ///
+    /// The UEFI spec only requires devices to support the colors for which
+    /// `Color::is_background` is true as background colors. Other background colors are passed
+    /// through, and it is up to the device whether to accept them.
+    pub fn set_attribute(&self, attribute: Attribute) -> Result<(), Status> {
+
+        (self._set_attribute)(self, attribute.as_raw())
             .as_result()?;
         Ok(())
     }
 
+    /// Returns the current foreground and background color
+    pub fn attribute(&self) -> Attribute {
+
+        self.mode.attribute()
+    }
+
+    /// Returns the current cursor position as a (column, row) pair
+    pub fn cursor_position(&self) -> (usize, usize) {
+
+        self.mode.cursor_position()
+    }
+
     /// Clears the screen with the currently set background color
     pub fn clear_screen(&self) -> Result<(), Status> {
 
@@ -236,6 +726,7 @@ impl<'a> fmt::Write for &'a SimpleTextOutput {
     fn write_str(&mut self, s: &str) -> fmt::Result {
 
         self.output_string(s)
+            .map(|_| ())
             .map_err(|_| fmt::Error)
     }
 }
@@ -243,61 +734,75 @@ impl<'a> fmt::Write for &'a SimpleTextOutput {
 
 /// Converts string to Char16 and calls the given function
 ///
-/// The UEFI spec represents strings using UTF-16, so Rust's `&str` type is not directly compatible.
-/// This function properly converts a `&str` to UTF-16, then calls the given function with a pointer
-/// to the UTF-16 string.
+/// The UEFI spec represents strings using UCS-2, so Rust's `&str` type is not directly compatible.
+/// This function converts a `&str` to UCS-2, then calls the given function with a pointer to the
+/// null-terminated result. Characters outside the Basic Multilingual Plane cannot be represented
+/// in UCS-2 and are replaced with U+FFFD.
 ///
-/// Since this is UEFI, there is no dynamic allocation, so the conversion actually happens 127
-/// characters at a time using a stack-allocated buffer. Because of this, `f` may actually be called
-/// more than one time, once for every 127 characters.
+/// Since this is UEFI, there is no dynamic allocation, so the conversion actually happens 127 code
+/// units at a time using a stack-allocated buffer. Because of this, `f` may actually be called
+/// more than one time, and a character is never split between two calls.
 ///
-/// TODO:
-///
-/// * if dynamic allocation becomes possible, this function might not be necessary
-fn exec_with_str<F>(string: &str, f: F) -> Result<(), Status>
-    where F: Fn(*const Char16) -> Status {
+/// Warnings returned by `f` do not stop processing, and the first one encountered is returned in
+/// the `Ok` variant. An error stops processing immediately, in which case any chunks passed to `f`
+/// before the error have already taken effect.
+fn exec_with_str<F>(string: &str, mut f: F) -> Result<Status, Status>
+    where F: FnMut(*const Char16) -> Status {
 
     // Allocate a buffer to encode the string piece by piece (can't do it all at once since
     // there is no dynamic allocation in this environment)
     const BUFSIZE: usize = 128;
-    let mut buf: [u16; BUFSIZE] = [0u16; BUFSIZE];
+    let mut buf: [Char16; BUFSIZE] = [0; BUFSIZE];
     let mut i = 0;
+    let mut status = Status::Success;
 
-    // Interpret the string as UTF-16 and fill the buffer
-    for c in string.chars() {
-        let mut char_buf: [u16; 2] = [0u16; 2];
-        buf[i] = c.encode_utf16(&mut char_buf)[0]; // TODO: this drops the second character
-        i += 1;
-
-        if i == BUFSIZE - 1 {
-            // Write out the string
-            // BUFSIZE - 1 ensures at least one null character is present
-            // TODO: what if this returns an error code?
-            f(buf.as_ptr())
-                .as_result()?;
-
-            // Fill the buffer back up with null characters
-            for j in 0..BUFSIZE {
-                buf[j] = 0u16;
-            }
+    for c in string.chars().map(to_ucs2) {
 
-            // Finally, reset our iterator
+        // Flush the buffer if this character does not fit
+        // BUFSIZE - 1 ensures there is always room for a null terminator
+        if i + c.len_utf16() > BUFSIZE - 1 {
+            buf[i] = 0;
+            status = first_warning(status, f(buf.as_ptr()).as_result()?);
             i = 0;
         }
+
+        i += c.encode_utf16(&mut buf[i..]).len();
     }
 
     // Flush whatever remains in the buffer
     if i != 0 {
-        f(buf.as_ptr())
-            .as_result()?;
+        buf[i] = 0;
+        status = first_warning(status, f(buf.as_ptr()).as_result()?);
+    }
+
+    Ok(status)
+}
+
+
+/// Replaces characters that cannot be represented in UCS-2
+fn to_ucs2(c: char) -> char {
+
+    if c.len_utf16() == 1 {
+        c
+    } else {
+        REPLACEMENT_CHARACTER
     }
+}
+
 
-    Ok(())
+/// Keeps the first warning out of a series of successful status codes
+fn first_warning(current: Status, next: Status) -> Status {
+
+    if current == Status::Success {
+        next
+    } else {
+        current
+    }
 }
 
 
 /// Colors supported by the UEFI console
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(usize)]
 pub enum Color {
     Black = 0x00,
@@ -331,6 +836,31 @@ impl Color {
             _ => false,
         }
     }
+
+    /// Returns the color with the given index in the console palette
+    ///
+    /// Only the lower four bits of `index` are used.
+    pub fn from_index(index: usize) -> Color {
+
+        match index & 0x0f {
+            0x00 => Color::Black,
+            0x01 => Color::Blue,
+            0x02 => Color::Green,
+            0x03 => Color::Cyan,
+            0x04 => Color::Red,
+            0x05 => Color::Magenta,
+            0x06 => Color::Brown,
+            0x07 => Color::LightGray,
+            0x08 => Color::DarkGray,
+            0x09 => Color::LightBlue,
+            0x0a => Color::LightGreen,
+            0x0b => Color::LightCyan,
+            0x0c => Color::LightRed,
+            0x0d => Color::LightMagenta,
+            0x0e => Color::Yellow,
+            _ => Color::White,
+        }
+    }
 }
 
 
@@ -346,6 +876,90 @@ pub struct SimpleTextOutputMode {
     pub cursor_visible: Bool,
 }
 
+impl SimpleTextOutputMode {
+
+    /// Returns the current foreground and background color
+    pub fn attribute(&self) -> Attribute {
+
+        Attribute::from_raw(self.attribute as usize)
+    }
+
+    /// Returns the current cursor position as a (column, row) pair
+    pub fn cursor_position(&self) -> (usize, usize) {
+
+        (self.cursor_column as usize, self.cursor_row as usize)
+    }
+
+    /// Tells whether the cursor is currently visible
+    pub fn cursor_visible(&self) -> bool {
+
+        match self.cursor_visible {
+            Bool::True => true,
+            Bool::False => false,
+        }
+    }
+}
+
+
+/// Foreground and background color of console text
+#[derive(Clone, Copy, Debug)]
+pub struct Attribute {
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Attribute {
+
+    /// Creates a new attribute from a foreground and background color
+    pub fn new(foreground: Color, background: Color) -> Attribute {
+
+        Attribute {
+            foreground: foreground,
+            background: background,
+        }
+    }
+
+    /// Decodes an attribute from its raw representation
+    pub fn from_raw(attribute: usize) -> Attribute {
+
+        Attribute {
+            foreground: Color::from_index(attribute & 0x0f),
+            background: Color::from_index((attribute >> 4) & 0x0f),
+        }
+    }
+
+    /// Encodes this attribute into its raw representation
+    pub fn as_raw(&self) -> usize {
+
+        ((self.background as usize) << 4) | (self.foreground as usize)
+    }
+}
+
+
+/// Iterator over the modes supported by an output device
+pub struct TextModes<'a> {
+    output: &'a SimpleTextOutput,
+    next: usize,
+}
+
+impl<'a> Iterator for TextModes<'a> {
+    type Item = (usize, ModeDescriptor);
+
+    fn next(&mut self) -> Option<(usize, ModeDescriptor)> {
+
+        let max_mode = self.output.mode.max_mode as usize;
+        while self.next < max_mode {
+            let mode_number = self.next;
+            self.next += 1;
+            if let Ok(desc) = self.output.query_mode(mode_number) {
+                return Some((mode_number, desc));
+            }
+        }
+
+        None
+    }
+}
+
 
 /// Describes the dimensions of an output device mode
 #[derive(Clone, Copy, Debug, Default)]
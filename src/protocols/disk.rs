@@ -0,0 +1,545 @@
+use core::{
+    cmp,
+    fmt,
+    ptr,
+};
+use {
+    boot_services::{
+        Guid,
+        Protocol,
+    },
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+        offset_position,
+    },
+    protocols::{
+        BlockIO2Token,
+        BlockIOMedia,
+    },
+    types::Status,
+};
+
+
+/// Provides access to a block device at arbitrary byte offsets
+#[repr(C)]
+pub struct DiskIO {
+    pub revision: u64,
+    pub _read_disk: extern "win64" fn(
+        this: &DiskIO,
+        media_id: u32,
+        offset: u64,
+        buffer_size: usize,
+        buffer: *mut u8
+    ) -> Status,
+    pub _write_disk: extern "win64" fn(
+        this: &DiskIO,
+        media_id: u32,
+        offset: u64,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status,
+}
+
+impl DiskIO {
+
+    /// Reads bytes from the disk, starting at the given byte offset
+    pub fn read_disk(&self, media_id: u32, offset: u64, buf: &mut [u8]) -> Result<(), Status> {
+
+        (self._read_disk)(self, media_id, offset, buf.len(), buf.as_mut_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Writes bytes to the disk, starting at the given byte offset
+    pub fn write_disk(&self, media_id: u32, offset: u64, buf: &[u8]) -> Result<(), Status> {
+
+        (self._write_disk)(self, media_id, offset, buf.len(), buf.as_ptr())
+            .as_result()?;
+        Ok(())
+    }
+}
+
+impl Protocol for DiskIO {
+
+    fn guid() -> &'static Guid { &DISK_IO_GUID }
+}
+
+impl fmt::Debug for DiskIO {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DiskIO")
+            .field("revision", &self.revision)
+            .finish()
+    }
+}
+
+
+/// Static Guid for DiskIO
+static DISK_IO_GUID: Guid = Guid {
+    data_1: 0xce345171,
+    data_2: 0xba0b,
+    data_3: 0x11d2,
+    data_4: [
+        0x8e,
+        0x4f,
+        0x00,
+        0xa0,
+        0xc9,
+        0x69,
+        0x72,
+        0x3b,
+    ],
+};
+
+
+/// Provides asynchronous access to a block device at arbitrary byte offsets
+///
+/// Asynchronous requests use the same token type as `BlockIO2`.
+#[repr(C)]
+pub struct DiskIO2 {
+    pub revision: u64,
+    pub _cancel: extern "win64" fn(this: &DiskIO2) -> Status,
+    pub _read_disk_ex: extern "win64" fn(
+        this: &DiskIO2,
+        media_id: u32,
+        offset: u64,
+        token: *mut BlockIO2Token,
+        buffer_size: usize,
+        buffer: *mut u8
+    ) -> Status,
+    pub _write_disk_ex: extern "win64" fn(
+        this: &DiskIO2,
+        media_id: u32,
+        offset: u64,
+        token: *mut BlockIO2Token,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status,
+    pub _flush_disk_ex: extern "win64" fn(this: &DiskIO2, token: *mut BlockIO2Token) -> Status,
+}
+
+impl DiskIO2 {
+
+    /// Cancels all outstanding asynchronous requests
+    pub fn cancel(&self) -> Result<(), Status> {
+
+        (self._cancel)(self)
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Reads bytes from the disk, waiting for the read to complete
+    pub fn read_disk(&self, media_id: u32, offset: u64, buf: &mut [u8]) -> Result<(), Status> {
+
+        (self._read_disk_ex)(self, media_id, offset, ptr::null_mut(), buf.len(), buf.as_mut_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Writes bytes to the disk, waiting for the write to complete
+    pub fn write_disk(&self, media_id: u32, offset: u64, buf: &[u8]) -> Result<(), Status> {
+
+        (self._write_disk_ex)(self, media_id, offset, ptr::null_mut(), buf.len(), buf.as_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Flushes all modified data to the physical device, waiting for the flush to complete
+    pub fn flush_disk(&self) -> Result<(), Status> {
+
+        (self._flush_disk_ex)(self, ptr::null_mut())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Starts reading bytes from the disk
+    ///
+    /// # Safety
+    ///
+    /// `token` and `buf` must not be moved, dropped or otherwise accessed until the token's event
+    /// has been signaled, because the device writes to them in the background.
+    pub unsafe fn read_disk_ex(
+        &self,
+        media_id: u32,
+        offset: u64,
+        token: &mut BlockIO2Token,
+        buf: &mut [u8]
+    ) -> Result<(), Status> {
+
+        (self._read_disk_ex)(self, media_id, offset, token, buf.len(), buf.as_mut_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Starts writing bytes to the disk
+    ///
+    /// # Safety
+    ///
+    /// `token` must not be moved, dropped or otherwise accessed, and `buf` must not be modified,
+    /// until the token's event has been signaled.
+    pub unsafe fn write_disk_ex(
+        &self,
+        media_id: u32,
+        offset: u64,
+        token: &mut BlockIO2Token,
+        buf: &[u8]
+    ) -> Result<(), Status> {
+
+        (self._write_disk_ex)(self, media_id, offset, token, buf.len(), buf.as_ptr())
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Starts flushing all modified data to the physical device
+    ///
+    /// # Safety
+    ///
+    /// `token` must not be moved, dropped or otherwise accessed until its event has been signaled.
+    pub unsafe fn flush_disk_ex(&self, token: &mut BlockIO2Token) -> Result<(), Status> {
+
+        (self._flush_disk_ex)(self, token)
+            .as_result()?;
+        Ok(())
+    }
+}
+
+impl Protocol for DiskIO2 {
+
+    fn guid() -> &'static Guid { &DISK_IO2_GUID }
+}
+
+impl fmt::Debug for DiskIO2 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DiskIO2")
+            .field("revision", &self.revision)
+            .finish()
+    }
+}
+
+
+/// Static Guid for DiskIO2
+static DISK_IO2_GUID: Guid = Guid {
+    data_1: 0x151c8eae,
+    data_2: 0x7f2c,
+    data_3: 0x472c,
+    data_4: [
+        0x9e,
+        0x54,
+        0x98,
+        0x28,
+        0x19,
+        0x4f,
+        0x6a,
+        0x88,
+    ],
+};
+
+
+/// Treats a disk as a stream of bytes with a current position
+///
+/// Reads and writes through the `Read` and `Write` traits start at the current position and
+/// advance it, and are truncated at the end of the disk.
+#[derive(Debug)]
+pub struct DiskStream<'a> {
+    disk_io: &'a DiskIO,
+    media_id: u32,
+    len: u64,
+    position: u64,
+}
+
+impl<'a> DiskStream<'a> {
+
+    /// Creates a stream over a disk of the given length in bytes
+    pub fn new(disk_io: &'a DiskIO, media_id: u32, len: u64) -> DiskStream<'a> {
+
+        DiskStream {
+            disk_io: disk_io,
+            media_id: media_id,
+            len: len,
+            position: 0,
+        }
+    }
+
+    /// Creates a stream over the media of the block device which provides `disk_io`
+    pub fn from_media(disk_io: &'a DiskIO, media: &BlockIOMedia) -> DiskStream<'a> {
+
+        let len = (media.last_block + 1) * media.block_size as u64;
+        DiskStream::new(disk_io, media.media_id, len)
+    }
+
+    /// Returns the length of the disk in bytes
+    pub fn len(&self) -> u64 {
+
+        self.len
+    }
+
+    /// Returns the current position in bytes
+    pub fn position(&self) -> u64 {
+
+        self.position
+    }
+
+    /// Sets the current position in bytes
+    ///
+    /// Positions past the end of the disk are allowed, but nothing can be read or written there.
+    pub fn set_position(&mut self, position: u64) {
+
+        self.position = position;
+    }
+
+    /// Limits a transfer so that it does not extend past the end of the disk
+    fn clamp(&self, len: usize) -> usize {
+
+        let remaining = self.len.saturating_sub(self.position);
+        cmp::min(len as u64, remaining) as usize
+    }
+}
+
+impl<'a> Read for DiskStream<'a> {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        let len = self.clamp(buf.len());
+        if len == 0 {
+            // The offset may be past the end of the disk, which the device would reject
+            return Ok(0);
+        }
+        self.disk_io.read_disk(self.media_id, self.position, &mut buf[..len])?;
+        self.position += len as u64;
+        Ok(len)
+    }
+}
+
+impl<'a> Write for DiskStream<'a> {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        let len = self.clamp(buf.len());
+        if len == 0 {
+            // The offset may be past the end of the disk, which the device would reject
+            return Ok(0);
+        }
+        self.disk_io.write_disk(self.media_id, self.position, &buf[..len])?;
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        Ok(())
+    }
+}
+
+impl<'a> Seek for DiskStream<'a> {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        self.position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => offset_position(self.len, offset)?,
+            SeekFrom::Current(offset) => offset_position(self.position, offset)?,
+        };
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use core::{
+        cell::RefCell,
+        slice,
+    };
+    use std::{
+        boxed::Box,
+        vec::Vec,
+    };
+    use super::*;
+
+    const DISK_SIZE: usize = 1000;
+    const MEDIA_ID: u32 = 3;
+
+    /// DiskIO backed by memory, which rejects transfers past the end of the disk
+    #[repr(C)]
+    struct MemoryDiskIO {
+        disk_io: DiskIO,
+        data: RefCell<Vec<u8>>,
+    }
+
+    impl MemoryDiskIO {
+
+        fn new() -> &'static MemoryDiskIO {
+
+            let data = (0..DISK_SIZE).map(|i| (i % 251) as u8).collect();
+
+            Box::leak(Box::new(MemoryDiskIO {
+                disk_io: DiskIO {
+                    revision: 0x0001_0000,
+                    _read_disk: read_disk,
+                    _write_disk: write_disk,
+                },
+                data: RefCell::new(data),
+            }))
+        }
+
+        fn stream(&self) -> DiskStream<'_> {
+
+            DiskStream::new(&self.disk_io, MEDIA_ID, DISK_SIZE as u64)
+        }
+
+        /// Returns the bytes covered by a transfer, if it is valid
+        fn range(
+            this: &DiskIO,
+            media_id: u32,
+            offset: u64,
+            buffer_size: usize
+        ) -> Result<(&MemoryDiskIO, usize, usize), Status> {
+
+            if media_id != MEDIA_ID {
+                return Err(Status::MediaChanged);
+            }
+            if offset + buffer_size as u64 > DISK_SIZE as u64 {
+                return Err(Status::InvalidParameter);
+            }
+
+            let this = unsafe { &*(this as *const DiskIO as *const MemoryDiskIO) };
+            Ok((this, offset as usize, offset as usize + buffer_size))
+        }
+    }
+
+    extern "win64" fn read_disk(
+        this: &DiskIO,
+        media_id: u32,
+        offset: u64,
+        buffer_size: usize,
+        buffer: *mut u8
+    ) -> Status {
+
+        match MemoryDiskIO::range(this, media_id, offset, buffer_size) {
+            Ok((this, start, end)) => {
+                let buf = unsafe { slice::from_raw_parts_mut(buffer, buffer_size) };
+                buf.copy_from_slice(&this.data.borrow()[start..end]);
+                Status::Success
+            },
+            Err(err) => err,
+        }
+    }
+
+    extern "win64" fn write_disk(
+        this: &DiskIO,
+        media_id: u32,
+        offset: u64,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status {
+
+        match MemoryDiskIO::range(this, media_id, offset, buffer_size) {
+            Ok((this, start, end)) => {
+                let buf = unsafe { slice::from_raw_parts(buffer, buffer_size) };
+                this.data.borrow_mut()[start..end].copy_from_slice(buf);
+                Status::Success
+            },
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn reads_at_unaligned_offsets() {
+
+        let disk = MemoryDiskIO::new();
+        let mut stream = disk.stream();
+
+        assert_eq!(stream.seek(SeekFrom::Start(509)), Ok(509));
+        let mut buf = [0; 7];
+        assert_eq!(stream.read(&mut buf), Ok(7));
+        assert_eq!(&buf[..], &disk.data.borrow()[509..516]);
+        assert_eq!(stream.position(), 516);
+    }
+
+    #[test]
+    fn truncates_reads_at_end_of_disk() {
+
+        let disk = MemoryDiskIO::new();
+        let mut stream = disk.stream();
+
+        assert_eq!(stream.seek(SeekFrom::End(-3)), Ok(DISK_SIZE as u64 - 3));
+        let mut buf = [0; 8];
+        assert_eq!(stream.read(&mut buf), Ok(3));
+        assert_eq!(&buf[..3], &disk.data.borrow()[DISK_SIZE - 3..]);
+        assert_eq!(stream.read(&mut buf), Ok(0));
+        assert_eq!(stream.stream_position(), Ok(DISK_SIZE as u64));
+    }
+
+    #[test]
+    fn writes_at_unaligned_offsets() {
+
+        let disk = MemoryDiskIO::new();
+        let mut stream = disk.stream();
+        let before = disk.data.borrow().clone();
+
+        stream.seek(SeekFrom::Start(13)).unwrap();
+        stream.write_all(&[0xaa; 5]).unwrap();
+        assert_eq!(stream.position(), 18);
+
+        // A write which reaches the end of the disk is truncated
+        stream.seek(SeekFrom::End(-2)).unwrap();
+        assert_eq!(stream.write(&[0xbb; 4]), Ok(2));
+
+        let after = disk.data.borrow();
+        assert_eq!(&after[..13], &before[..13]);
+        assert_eq!(&after[13..18], &[0xaa; 5]);
+        assert_eq!(&after[18..DISK_SIZE - 2], &before[18..DISK_SIZE - 2]);
+        assert_eq!(&after[DISK_SIZE - 2..], &[0xbb; 2]);
+    }
+
+    #[test]
+    fn seeks_relative_to_end_and_current_position() {
+
+        let disk = MemoryDiskIO::new();
+        let mut stream = disk.stream();
+        let len = DISK_SIZE as u64;
+        assert_eq!(stream.len(), len);
+
+        assert_eq!(stream.seek(SeekFrom::Current(-1)), Err(Status::InvalidParameter));
+        assert_eq!(stream.seek(SeekFrom::End(-(len as i64) - 1)), Err(Status::InvalidParameter));
+        assert_eq!(stream.stream_position(), Ok(0));
+
+        assert_eq!(stream.seek(SeekFrom::End(-4)), Ok(len - 4));
+        assert_eq!(stream.seek(SeekFrom::Current(-4)), Ok(len - 8));
+        let mut buf = [0; 16];
+        assert_eq!(stream.read(&mut buf), Ok(8));
+        assert_eq!(&buf[..8], &disk.data.borrow()[len as usize - 8..]);
+    }
+
+    #[test]
+    fn stops_past_end_of_disk() {
+
+        let disk = MemoryDiskIO::new();
+        let mut stream = disk.stream();
+        let len = stream.len();
+
+        // Seeking past the end is allowed, but nothing can be transferred there
+        assert_eq!(stream.seek(SeekFrom::End(10)), Ok(len + 10));
+        let mut buf = [0; 4];
+        assert_eq!(stream.read(&mut buf), Ok(0));
+        assert_eq!(stream.read_exact(&mut buf), Err(Status::EndOfFile));
+        assert_eq!(stream.write(&buf), Ok(0));
+        assert_eq!(stream.write_all(&buf), Err(Status::VolumeFull));
+        assert_eq!(stream.position(), len + 10);
+    }
+
+    #[test]
+    fn keeps_position_when_transfer_fails() {
+
+        let disk = MemoryDiskIO::new();
+        let mut stream = DiskStream::new(&disk.disk_io, MEDIA_ID + 1, DISK_SIZE as u64);
+
+        stream.set_position(4);
+        let mut buf = [0; 4];
+        assert_eq!(stream.read(&mut buf), Err(Status::MediaChanged));
+        assert_eq!(stream.write(&buf), Err(Status::MediaChanged));
+        assert_eq!(stream.position(), 4);
+    }
+}
@@ -0,0 +1,185 @@
+use core::{
+    fmt,
+    slice,
+};
+use {
+    boot_services::{
+        Guid,
+        Protocol,
+    },
+    types::{
+        Char16,
+        Handle,
+        Status,
+    },
+};
+
+
+/// Allows the firmware to connect a driver to the controllers it manages
+///
+/// Drivers written in Rust normally implement `driver::Driver` rather than filling in this
+/// structure directly.
+#[repr(C)]
+pub struct DriverBinding {
+    pub _supported: extern "win64" fn(
+        this: &DriverBinding,
+        controller_handle: Handle,
+        remaining_device_path: *const ()
+    ) -> Status,
+    pub _start: extern "win64" fn(
+        this: &DriverBinding,
+        controller_handle: Handle,
+        remaining_device_path: *const ()
+    ) -> Status,
+    pub _stop: extern "win64" fn(
+        this: &DriverBinding,
+        controller_handle: Handle,
+        number_of_children: usize,
+        child_handle_buffer: *const Handle
+    ) -> Status,
+    pub version: u32,
+    pub image_handle: Handle,
+    pub driver_binding_handle: Handle,
+}
+
+impl Protocol for DriverBinding {
+
+    fn guid() -> &'static Guid { &DRIVER_BINDING_GUID }
+}
+
+impl fmt::Debug for DriverBinding {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("DriverBinding")
+            .field("version", &self.version)
+            .field("image_handle", &self.image_handle)
+            .field("driver_binding_handle", &self.driver_binding_handle)
+            .finish()
+    }
+}
+
+
+/// Static Guid for DriverBinding
+static DRIVER_BINDING_GUID: Guid = Guid {
+    data_1: 0x18a031ab,
+    data_2: 0xb443,
+    data_3: 0x4d1a,
+    data_4: [
+        0xa5,
+        0xc0,
+        0x0c,
+        0x09,
+        0x26,
+        0x1e,
+        0x9f,
+        0x71,
+    ],
+};
+
+
+/// Provides user-readable names for a driver and the controllers it manages
+#[repr(C)]
+pub struct ComponentName2 {
+    pub _get_driver_name: extern "win64" fn(
+        this: &ComponentName2,
+        language: *const u8,
+        driver_name: &mut *const Char16
+    ) -> Status,
+    pub _get_controller_name: extern "win64" fn(
+        this: &ComponentName2,
+        controller_handle: Handle,
+        child_handle: Handle,
+        language: *const u8,
+        controller_name: &mut *const Char16
+    ) -> Status,
+    pub supported_languages: *const u8,
+}
+
+impl ComponentName2 {
+
+    /// Returns the null-terminated name of the driver in the given RFC 4646 language
+    ///
+    /// `language` must be null-terminated, such as `b"en\0"`, or `Status::InvalidParameter` is
+    /// returned.
+    pub fn driver_name(&self, language: &[u8]) -> Result<&[Char16], Status> {
+
+        if language.last() != Some(&0) {
+            return Err(Status::InvalidParameter);
+        }
+
+        let mut name = 0 as *const Char16;
+        (self._get_driver_name)(self, language.as_ptr(), &mut name)
+            .as_result()?;
+
+        Ok(unsafe { null_terminated(name) })
+    }
+
+    /// Returns the null-terminated name of a controller managed by the driver
+    ///
+    /// If `child_handle` is given, the name of that child controller is returned instead. As with
+    /// `driver_name`, `language` must be null-terminated.
+    pub fn controller_name(
+        &self,
+        controller_handle: Handle,
+        child_handle: Option<Handle>,
+        language: &[u8]
+    ) -> Result<&[Char16], Status> {
+
+        if language.last() != Some(&0) {
+            return Err(Status::InvalidParameter);
+        }
+
+        let mut name = 0 as *const Char16;
+        (self._get_controller_name)(
+            self,
+            controller_handle,
+            child_handle.unwrap_or(0),
+            language.as_ptr(),
+            &mut name
+        )
+            .as_result()?;
+
+        Ok(unsafe { null_terminated(name) })
+    }
+}
+
+impl Protocol for ComponentName2 {
+
+    fn guid() -> &'static Guid { &COMPONENT_NAME2_GUID }
+}
+
+impl fmt::Debug for ComponentName2 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ComponentName2")
+            .finish()
+    }
+}
+
+
+/// Static Guid for ComponentName2
+static COMPONENT_NAME2_GUID: Guid = Guid {
+    data_1: 0x6a7a5cff,
+    data_2: 0xe8d9,
+    data_3: 0x4f70,
+    data_4: [
+        0xba,
+        0xda,
+        0x75,
+        0xab,
+        0x30,
+        0x25,
+        0xce,
+        0x14,
+    ],
+};
+
+
+/// Creates a slice over a null-terminated UTF-16 string, including the terminator
+unsafe fn null_terminated<'a>(ptr: *const Char16) -> &'a [Char16] {
+
+    let mut len = 0;
+    while *ptr.offset(len as isize) != 0 {
+        len += 1;
+    }
+
+    slice::from_raw_parts(ptr, len + 1)
+}
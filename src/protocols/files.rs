@@ -1,6 +1,9 @@
 use core::{
+    cmp,
     mem,
+    ops,
     ops::Drop,
+    ptr,
     slice,
 };
 
@@ -12,12 +15,20 @@ use {
         Protocol,
         utf16_to_str,
     },
+    io::{
+        Read,
+        Seek,
+        SeekFrom,
+        Write,
+        offset_position,
+    },
     types::{
         Bool,
         Char16,
         EfiBs,
         MemoryType,
         Status,
+        Time,
     },
 };
 
@@ -34,19 +45,28 @@ pub struct File {
         attributes: FileAttributes
     ) -> Status,
     pub _close: extern "win64" fn(this: &File) -> Status,
-    pub _delete: extern "win64" fn() -> Status,
+    pub _delete: extern "win64" fn(this: &File) -> Status,
     pub _read: extern "win64" fn(this: &File, buffer_size: &mut usize, buffer: *mut u8) -> Status,
-    pub _write: extern "win64" fn() -> Status,
-    pub _get_position: extern "win64" fn() -> Status,
-    pub _set_position: extern "win64" fn() -> Status,
+    pub _write: extern "win64" fn(
+        this: &File,
+        buffer_size: &mut usize,
+        buffer: *const u8
+    ) -> Status,
+    pub _get_position: extern "win64" fn(this: &File, position: &mut u64) -> Status,
+    pub _set_position: extern "win64" fn(this: &File, position: u64) -> Status,
     pub _get_info: extern "win64" fn(
         this: &File,
         information_type: &Guid,
         buffer_size: &mut usize,
         buffer: *mut u8
     ) -> Status,
-    pub _set_info: extern "win64" fn() -> Status,
-    pub _flush: extern "win64" fn() -> Status,
+    pub _set_info: extern "win64" fn(
+        this: &File,
+        information_type: &Guid,
+        buffer_size: usize,
+        buffer: *const u8
+    ) -> Status,
+    pub _flush: extern "win64" fn(this: &File) -> Status,
 }
 
 impl File {
@@ -90,6 +110,43 @@ impl File {
             .map(|_| len)
     }
 
+    /// Writes data to this file, returning the number of bytes written
+    pub fn write(&self, buf: &[u8]) -> Result<usize, Status> {
+
+        let mut len = buf.len();
+        (self._write)(self, &mut len, buf.as_ptr())
+            .as_result()
+            .map(|_| len)
+    }
+
+    /// Returns the current position within this file
+    pub fn position(&self) -> Result<u64, Status> {
+
+        let mut position = 0;
+        (self._get_position)(self, &mut position)
+            .as_result()
+            .map(|_| position)
+    }
+
+    /// Sets the current position within this file
+    ///
+    /// Setting the position to `u64::MAX` moves it to the end of the file. The position of a
+    /// directory may only be set to 0, which restarts the reading of its entries.
+    pub fn set_position(&self, position: u64) -> Result<(), Status> {
+
+        (self._set_position)(self, position)
+            .as_result()
+            .map(|_| ())
+    }
+
+    /// Flushes all modified data associated with this file to the device
+    pub fn flush(&self) -> Result<(), Status> {
+
+        (self._flush)(self)
+            .as_result()
+            .map(|_| ())
+    }
+
     /// Returns information about a file
     pub fn get_info<'a, T>(&self, boot_services: &'a BootServices) -> Result<Pool<'a, T>, Status>
         where T: FileInformationType + Sized {
@@ -113,6 +170,40 @@ impl File {
             .as_result()
             .map(|_| unsafe { Pool::new_unchecked(buf as *mut T, boot_services) })
     }
+
+    /// Sets information about a file
+    pub fn set_info<T>(&self, info: &T) -> Result<(), Status>
+        where T: FileInformationType {
+
+        (self._set_info)(self, T::guid(), info.buffer_size(), info as *const T as *const u8)
+            .as_result()
+            .map(|_| ())
+    }
+
+    /// Changes the attributes of this file
+    ///
+    /// The `DIRECTORY` attribute cannot be changed, so it is preserved regardless of `attributes`.
+    pub fn set_attributes(
+        &self,
+        attributes: FileAttributes,
+        boot_services: &BootServices
+    ) -> Result<(), Status> {
+
+        let mut info = self.get_info::<FileInfo>(boot_services)?;
+        info.attribute = (attributes - FileAttributes::DIRECTORY) |
+            (info.attribute & FileAttributes::DIRECTORY);
+        self.set_info(&*info)
+    }
+
+    /// Changes the label of the volume containing this file
+    pub fn set_volume_label(
+        &self,
+        label: &str,
+        boot_services: &BootServices
+    ) -> Result<(), Status> {
+
+        self.set_info(&*FileSystemVolumeLabel::new(label, boot_services)?)
+    }
 }
 
 impl Drop for File {
@@ -124,6 +215,121 @@ impl Drop for File {
 }
 
 
+/// A File which is closed when dropped
+///
+/// `File::open` and `SimpleFileSystem::open_volume` return bare pointers, which are never closed.
+/// Wrapping them in an `OwnedFile` ensures the file handle is released.
+pub struct OwnedFile(EfiBs<File>);
+
+impl OwnedFile {
+
+    /// Takes ownership of an open file
+    pub fn new(file: EfiBs<File>) -> OwnedFile {
+
+        OwnedFile(file)
+    }
+
+    /// Closes and deletes this file
+    ///
+    /// Fails with `Status::WarnDeleteFailure` if the file was closed but could not be deleted.
+    pub fn delete(self) -> Result<(), Status> {
+
+        let res = (self._delete)(&self.0);
+        mem::forget(self);
+        match res {
+            Status::Success => Ok(()),
+            res => Err(res),
+        }
+    }
+}
+
+impl ops::Deref for OwnedFile {
+    type Target = File;
+
+    fn deref(&self) -> &File {
+
+        &self.0
+    }
+}
+
+impl<'a> Read for &'a File {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        File::read(*self, buf)
+    }
+}
+
+impl<'a> Write for &'a File {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        File::write(*self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        File::flush(*self)
+    }
+}
+
+impl<'a> Seek for &'a File {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        let position = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => {
+                // Moving to u64::MAX moves to the end of the file, revealing its size
+                File::set_position(*self, u64::MAX)?;
+                offset_position(self.position()?, offset)?
+            },
+            SeekFrom::Current(offset) => offset_position(self.position()?, offset)?,
+        };
+
+        File::set_position(*self, position)?;
+        Ok(position)
+    }
+}
+
+impl Read for OwnedFile {
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Status> {
+
+        File::read(self, buf)
+    }
+}
+
+impl Write for OwnedFile {
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Status> {
+
+        File::write(self, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Status> {
+
+        File::flush(self)
+    }
+}
+
+impl Seek for OwnedFile {
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Status> {
+
+        (&**self).seek(pos)
+    }
+}
+
+impl Drop for OwnedFile {
+
+    fn drop(&mut self) {
+
+        let _ = (self._close)(&self.0);
+    }
+}
+
+
 bitflags! {
     /// Attribute bits for a file
     pub struct FileAttributes: u64 {
@@ -152,6 +358,9 @@ bitflags! {
 pub trait FileInformationType {
 
     fn guid() -> &'static Guid;
+
+    /// Returns the size of this information in bytes, including any trailing name
+    fn buffer_size(&self) -> usize;
 }
 
 
@@ -162,16 +371,51 @@ pub struct FileInfo {
     pub size: u64,
     pub file_size: u64,
     pub physical_size: u64,
-    pub create_time: usize, // TODO
-    pub last_access_time: usize, // TODO
-    pub modification_time: usize, // TODO
-    pub attribute: u64, // TODO
+    pub create_time: Time,
+    pub last_access_time: Time,
+    pub modification_time: Time,
+    pub attribute: FileAttributes,
     _file_name: Char16,
 }
 
+/// Offset of the file name within FileInfo
+const FILE_INFO_NAME_OFFSET: usize = 80;
+
+impl FileInfo {
+
+    /// Returns the file name, without a null terminator
+    pub fn file_name(&self) -> &[Char16] {
+
+        trailing_name(self, FILE_INFO_NAME_OFFSET)
+    }
+
+    /// Returns a copy of this information with a different file name
+    ///
+    /// Passing the copy to `File::set_info` renames the file. A name beginning with a separator
+    /// is relative to the root of the volume, which allows the file to be moved.
+    pub fn with_file_name<'a>(
+        &self,
+        file_name: &str,
+        boot_services: &'a BootServices
+    ) -> Result<Pool<'a, FileInfo>, Status> {
+
+        let mut info = with_trailing_name(self, FILE_INFO_NAME_OFFSET, file_name, boot_services)?;
+        info.size = (FILE_INFO_NAME_OFFSET + (file_name.encode_utf16().count() + 1) * 2) as u64;
+        Ok(info)
+    }
+
+    /// Tells whether this is a directory
+    pub fn is_directory(&self) -> bool {
+
+        self.attribute.contains(FileAttributes::DIRECTORY)
+    }
+}
+
 impl FileInformationType for FileInfo {
 
     fn guid() -> &'static Guid { &FILE_INFO_GUID }
+
+    fn buffer_size(&self) -> usize { self.size as usize }
 }
 
 
@@ -187,6 +431,9 @@ pub struct FileSystemInfo {
     _volume_label: Char16,
 }
 
+/// Offset of the volume label within FileSystemInfo
+const FILE_SYSTEM_INFO_LABEL_OFFSET: usize = 36;
+
 impl FileSystemInfo {
 
     /// Gets the volume label
@@ -195,18 +442,127 @@ impl FileSystemInfo {
         boot_services: &'a BootServices
     ) -> Result<Pool<'a, str>, Status> {
 
-        let buf = unsafe {
-            let buf_size = self._size - (mem::size_of::<FileSystemInfo>() - mem::size_of::<Char16>());
-            slice::from_raw_parts(&(self._volume_label), buf_size)
-        };
+        utf16_to_str(trailing_name(self, FILE_SYSTEM_INFO_LABEL_OFFSET), boot_services)
+    }
+
+    /// Returns a copy of this information with a different volume label
+    ///
+    /// Passing the copy to `File::set_info` on any file of the volume changes its label. Only the
+    /// label can be changed this way.
+    pub fn with_volume_label<'a>(
+        &self,
+        volume_label: &str,
+        boot_services: &'a BootServices
+    ) -> Result<Pool<'a, FileSystemInfo>, Status> {
 
-        utf16_to_str(buf, boot_services)
+        let offset = FILE_SYSTEM_INFO_LABEL_OFFSET;
+        let mut info = with_trailing_name(self, offset, volume_label, boot_services)?;
+        info._size = offset + (volume_label.encode_utf16().count() + 1) * 2;
+        Ok(info)
     }
 }
 
 impl FileInformationType for FileSystemInfo {
 
     fn guid() -> &'static Guid { &FILE_SYSTEM_INFO_GUID }
+
+    fn buffer_size(&self) -> usize { self._size }
+}
+
+
+/// Label of the system volume
+#[derive(Debug)]
+#[repr(C)]
+pub struct FileSystemVolumeLabel {
+    _volume_label: Char16,
+}
+
+impl FileSystemVolumeLabel {
+
+    /// Creates volume label information, for use with `File::set_info`
+    pub fn new<'a>(
+        volume_label: &str,
+        boot_services: &'a BootServices
+    ) -> Result<Pool<'a, FileSystemVolumeLabel>, Status> {
+
+        let empty = FileSystemVolumeLabel { _volume_label: 0 };
+        with_trailing_name(&empty, 0, volume_label, boot_services)
+    }
+
+    /// Gets the volume label
+    pub fn volume_label<'a>(
+        &self,
+        boot_services: &'a BootServices
+    ) -> Result<Pool<'a, str>, Status> {
+
+        utf16_to_str(trailing_name(self, 0), boot_services)
+    }
+}
+
+impl FileInformationType for FileSystemVolumeLabel {
+
+    fn guid() -> &'static Guid { &FILE_SYSTEM_VOLUME_LABEL_GUID }
+
+    /// This type has no size field, so the size is determined by the label's null terminator
+    fn buffer_size(&self) -> usize {
+
+        let mut len = 0;
+        unsafe {
+            while *(&self._volume_label as *const Char16).offset(len as isize) != 0 {
+                len += 1;
+            }
+        }
+
+        (len + 1) * 2
+    }
+}
+
+
+/// Returns the UTF-16 name which ends a variable-length information type, without a null
+/// terminator
+///
+/// The name starts `offset` bytes into `info` and extends to the end of its buffer, as given by
+/// `buffer_size`, or to the first null if that comes sooner.
+fn trailing_name<T>(info: &T, offset: usize) -> &[Char16]
+    where T: FileInformationType {
+
+    let len = info.buffer_size().saturating_sub(offset) / mem::size_of::<Char16>();
+    let name = unsafe {
+        let ptr = (info as *const T as *const u8).offset(offset as isize) as *const Char16;
+        slice::from_raw_parts(ptr, len)
+    };
+
+    let len = name.iter().position(|c| *c == 0).unwrap_or(len);
+    &name[..len]
+}
+
+
+/// Copies the first `offset` bytes of a variable-length information type into pool memory,
+/// followed by a new null-terminated name
+///
+/// The caller is responsible for updating any size field of the copy.
+fn with_trailing_name<'a, T>(
+    info: &T,
+    offset: usize,
+    name: &str,
+    boot_services: &'a BootServices
+) -> Result<Pool<'a, T>, Status>
+    where T: FileInformationType {
+
+    let len = offset + (name.encode_utf16().count() + 1) * mem::size_of::<Char16>();
+    let len = cmp::max(len, mem::size_of::<T>());
+    let buf = boot_services.allocate_pool(MemoryType::LoaderData, len)?;
+    unsafe {
+        ptr::copy_nonoverlapping(info as *const T as *const u8, buf, offset);
+        let name_buf = buf.offset(offset as isize) as *mut Char16;
+        let mut i = 0;
+        for unit in name.encode_utf16().chain(Some(0)) {
+            *name_buf.offset(i) = unit;
+            i += 1;
+        }
+
+        Ok(Pool::new_unchecked(buf as *mut T, boot_services))
+    }
 }
 
 
@@ -292,3 +648,20 @@ static SIMPLE_FILE_SYSTEM_GUID: Guid = Guid {
         0x3b,
     ],
 };
+
+
+static FILE_SYSTEM_VOLUME_LABEL_GUID: Guid = Guid {
+    data_1: 0xdb47d7d3,
+    data_2: 0xfe81,
+    data_3: 0x11d3,
+    data_4: [
+        0x9a,
+        0x35,
+        0x00,
+        0x90,
+        0x27,
+        0x3f,
+        0xc1,
+        0x4d,
+    ],
+};
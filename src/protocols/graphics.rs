@@ -0,0 +1,628 @@
+use core::{
+    cmp,
+    fmt,
+    marker::PhantomData,
+    ptr,
+};
+use {
+    boot_services::{
+        BootServices,
+        Guid,
+        Pool,
+        Protocol,
+    },
+    types::{
+        EfiBs,
+        PhysicalAddress,
+        Status,
+    },
+};
+
+
+/// Provides access to a graphics device and its framebuffer
+#[repr(C)]
+pub struct GraphicsOutput {
+    pub _query_mode: extern "win64" fn(
+        this: &GraphicsOutput,
+        mode_number: u32,
+        size_of_info: &mut usize,
+        info: &mut *mut ModeInformation
+    ) -> Status,
+    pub _set_mode: extern "win64" fn(this: &GraphicsOutput, mode_number: u32) -> Status,
+    pub _blt: extern "win64" fn(
+        this: &GraphicsOutput,
+        blt_buffer: *mut BltPixel,
+        blt_operation: BltOperation,
+        source_x: usize,
+        source_y: usize,
+        destination_x: usize,
+        destination_y: usize,
+        width: usize,
+        height: usize,
+        delta: usize
+    ) -> Status,
+    pub mode: EfiBs<GraphicsOutputMode>,
+}
+
+impl GraphicsOutput {
+
+    /// Returns information about the specified mode
+    pub fn query_mode<'a>(
+        &self,
+        mode_number: u32,
+        boot_services: &'a BootServices
+    ) -> Result<Pool<'a, ModeInformation>, Status> {
+
+        let mut size_of_info = 0;
+        let mut info = ptr::null_mut();
+        (self._query_mode)(self, mode_number, &mut size_of_info, &mut info)
+            .as_result()?;
+
+        if info.is_null() {
+            Err(Status::NotFound)
+        } else {
+            Ok(unsafe { Pool::new_unchecked(info, boot_services) })
+        }
+    }
+
+    /// Returns an iterator over the modes supported by the graphics device
+    ///
+    /// Modes which cannot be queried are skipped.
+    pub fn modes<'a>(&'a self, boot_services: &'a BootServices) -> GraphicsModes<'a> {
+
+        GraphicsModes {
+            output: self,
+            boot_services: boot_services,
+            next: 0,
+        }
+    }
+
+    /// Sets the video device into the specified mode and clears the visible portion of the
+    /// framebuffer to black
+    ///
+    /// Any `FrameBuffer` obtained before the mode change must no longer be used.
+    pub fn set_mode(&self, mode_number: u32) -> Result<(), Status> {
+
+        (self._set_mode)(self, mode_number)
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Returns information about the current mode
+    pub fn current_mode(&self) -> &ModeInformation {
+
+        &*self.mode.info
+    }
+
+    /// Returns the framebuffer for the current mode
+    ///
+    /// Fails with `Status::Unsupported` if the current mode does not allow direct framebuffer
+    /// access, in which case the `blt_*` methods must be used instead.
+    pub fn frame_buffer<'a>(&'a self) -> Result<FrameBuffer<'a>, Status> {
+
+        let info = self.current_mode();
+        match info.pixel_format {
+            PixelFormat::RED_GREEN_BLUE_RESERVED_8_BIT_PER_COLOR |
+            PixelFormat::BLUE_GREEN_RED_RESERVED_8_BIT_PER_COLOR |
+            PixelFormat::BIT_MASK => { },
+            _ => return Err(Status::Unsupported),
+        }
+
+        Ok(FrameBuffer {
+            base: self.mode.frame_buffer_base,
+            size: self.mode.frame_buffer_size,
+            width: info.horizontal_resolution as usize,
+            height: info.vertical_resolution as usize,
+            stride: info.pixels_per_scan_line as usize,
+            pixel_format: info.pixel_format,
+            pixel_information: info.pixel_information,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Fills a rectangle on the screen with a single color
+    pub fn blt_video_fill(
+        &self,
+        pixel: BltPixel,
+        destination: (usize, usize),
+        size: (usize, usize)
+    ) -> Result<(), Status> {
+
+        let mut pixel = pixel;
+        (self._blt)(
+            self,
+            &mut pixel,
+            BltOperation::VideoFill,
+            0,
+            0,
+            destination.0,
+            destination.1,
+            size.0,
+            size.1,
+            0
+        )
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Copies a rectangle from the screen into a buffer
+    ///
+    /// `buffer` is treated as an image whose rows are `buffer_width` pixels wide.
+    pub fn blt_video_to_buffer(
+        &self,
+        buffer: &mut [BltPixel],
+        buffer_width: usize,
+        source: (usize, usize),
+        destination: (usize, usize),
+        size: (usize, usize)
+    ) -> Result<(), Status> {
+
+        check_blt_buffer(buffer.len(), buffer_width, destination, size)?;
+        (self._blt)(
+            self,
+            buffer.as_mut_ptr(),
+            BltOperation::VideoToBltBuffer,
+            source.0,
+            source.1,
+            destination.0,
+            destination.1,
+            size.0,
+            size.1,
+            buffer_width * BltPixel::SIZE
+        )
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Copies a rectangle from a buffer onto the screen
+    ///
+    /// `buffer` is treated as an image whose rows are `buffer_width` pixels wide.
+    pub fn blt_buffer_to_video(
+        &self,
+        buffer: &[BltPixel],
+        buffer_width: usize,
+        source: (usize, usize),
+        destination: (usize, usize),
+        size: (usize, usize)
+    ) -> Result<(), Status> {
+
+        check_blt_buffer(buffer.len(), buffer_width, source, size)?;
+        // The buffer is only read by this operation
+        (self._blt)(
+            self,
+            buffer.as_ptr() as *mut BltPixel,
+            BltOperation::BufferToVideo,
+            source.0,
+            source.1,
+            destination.0,
+            destination.1,
+            size.0,
+            size.1,
+            buffer_width * BltPixel::SIZE
+        )
+            .as_result()?;
+        Ok(())
+    }
+
+    /// Copies a rectangle from one location on the screen to another
+    pub fn blt_video_to_video(
+        &self,
+        source: (usize, usize),
+        destination: (usize, usize),
+        size: (usize, usize)
+    ) -> Result<(), Status> {
+
+        (self._blt)(
+            self,
+            ptr::null_mut(),
+            BltOperation::VideoToVideo,
+            source.0,
+            source.1,
+            destination.0,
+            destination.1,
+            size.0,
+            size.1,
+            0
+        )
+            .as_result()?;
+        Ok(())
+    }
+}
+
+impl Protocol for GraphicsOutput {
+
+    fn guid() -> &'static Guid { &GRAPHICS_OUTPUT_GUID }
+}
+
+impl fmt::Debug for GraphicsOutput {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("GraphicsOutput")
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+
+/// Static Guid for GraphicsOutput
+static GRAPHICS_OUTPUT_GUID: Guid = Guid {
+    data_1: 0x9042a9de,
+    data_2: 0x23dc,
+    data_3: 0x4a38,
+    data_4: [
+        0x96,
+        0xfb,
+        0x7a,
+        0xde,
+        0xd0,
+        0x80,
+        0x51,
+        0x6a,
+    ],
+};
+
+
+/// Makes sure a rectangle lies entirely within a Blt buffer
+fn check_blt_buffer(
+    len: usize,
+    buffer_width: usize,
+    position: (usize, usize),
+    size: (usize, usize)
+) -> Result<(), Status> {
+
+    if size.0 == 0 || size.1 == 0 {
+        return Ok(());
+    }
+
+    let row_end = position.0.checked_add(size.0).ok_or(Status::InvalidParameter)?;
+    let end = position.1
+        .checked_add(size.1 - 1)
+        .and_then(|last_row| last_row.checked_mul(buffer_width))
+        .and_then(|row_start| row_start.checked_add(row_end));
+    match end {
+        Some(end) if row_end <= buffer_width && end <= len => Ok(()),
+        _ => Err(Status::InvalidParameter),
+    }
+}
+
+
+/// Describes the current mode of a graphics device
+#[derive(Debug)]
+#[repr(C)]
+pub struct GraphicsOutputMode {
+    pub max_mode: u32,
+    pub mode: u32,
+    pub info: EfiBs<ModeInformation>,
+    pub size_of_info: usize,
+    pub frame_buffer_base: PhysicalAddress,
+    pub frame_buffer_size: usize,
+}
+
+
+/// Describes a mode supported by a graphics device
+#[derive(Debug)]
+#[repr(C)]
+pub struct ModeInformation {
+    pub version: u32,
+    pub horizontal_resolution: u32,
+    pub vertical_resolution: u32,
+    pub pixel_format: PixelFormat,
+    pub pixel_information: PixelBitmask,
+    pub pixels_per_scan_line: u32,
+}
+
+
+/// Layout of a pixel in the framebuffer
+///
+/// This is not an enum, since firmware may report values which are not defined here.
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct PixelFormat(pub u32);
+
+impl PixelFormat {
+    pub const RED_GREEN_BLUE_RESERVED_8_BIT_PER_COLOR: PixelFormat = PixelFormat(0);
+    pub const BLUE_GREEN_RED_RESERVED_8_BIT_PER_COLOR: PixelFormat = PixelFormat(1);
+    pub const BIT_MASK: PixelFormat = PixelFormat(2);
+    pub const BLT_ONLY: PixelFormat = PixelFormat(3);
+}
+
+impl fmt::Debug for PixelFormat {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PixelFormat::RED_GREEN_BLUE_RESERVED_8_BIT_PER_COLOR =>
+                fmt.write_str("RedGreenBlueReserved8BitPerColor"),
+            PixelFormat::BLUE_GREEN_RED_RESERVED_8_BIT_PER_COLOR =>
+                fmt.write_str("BlueGreenRedReserved8BitPerColor"),
+            PixelFormat::BIT_MASK => fmt.write_str("BitMask"),
+            PixelFormat::BLT_ONLY => fmt.write_str("BltOnly"),
+            PixelFormat(value) => fmt.debug_tuple("PixelFormat").field(&value).finish(),
+        }
+    }
+}
+
+
+/// Bits used for each color channel when the pixel format is `PixelFormat::BIT_MASK`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct PixelBitmask {
+    pub red_mask: u32,
+    pub green_mask: u32,
+    pub blue_mask: u32,
+    pub reserved_mask: u32,
+}
+
+
+/// A pixel as used by Blt operations
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct BltPixel {
+    pub blue: u8,
+    pub green: u8,
+    pub red: u8,
+    pub reserved: u8,
+}
+
+impl BltPixel {
+
+    /// Size of a pixel in bytes
+    pub const SIZE: usize = 4;
+
+    /// Creates a pixel from its red, green and blue components
+    pub fn new(red: u8, green: u8, blue: u8) -> BltPixel {
+
+        BltPixel {
+            blue: blue,
+            green: green,
+            red: red,
+            reserved: 0,
+        }
+    }
+}
+
+
+/// Operation performed by a Blt
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub enum BltOperation {
+    VideoFill,
+    VideoToBltBuffer,
+    BufferToVideo,
+    VideoToVideo,
+}
+
+
+/// Iterator over the modes supported by a graphics device
+pub struct GraphicsModes<'a> {
+    output: &'a GraphicsOutput,
+    boot_services: &'a BootServices,
+    next: u32,
+}
+
+impl<'a> Iterator for GraphicsModes<'a> {
+    type Item = (u32, Pool<'a, ModeInformation>);
+
+    fn next(&mut self) -> Option<(u32, Pool<'a, ModeInformation>)> {
+
+        while self.next < self.output.mode.max_mode {
+            let mode_number = self.next;
+            self.next += 1;
+            if let Ok(info) = self.output.query_mode(mode_number, self.boot_services) {
+                return Some((mode_number, info));
+            }
+        }
+
+        None
+    }
+}
+
+
+/// Direct access to the framebuffer of a graphics device
+///
+/// Pixels are converted to and from the pixel format of the mode that was current when the
+/// `FrameBuffer` was obtained. The framebuffer remains accessible after exiting boot services, but
+/// must not be used after the mode is changed.
+pub struct FrameBuffer<'a> {
+    base: PhysicalAddress,
+    size: usize,
+    width: usize,
+    height: usize,
+    stride: usize,
+    pixel_format: PixelFormat,
+    pixel_information: PixelBitmask,
+    _marker: PhantomData<&'a GraphicsOutput>,
+}
+
+impl<'a> FrameBuffer<'a> {
+
+    /// Returns the horizontal resolution in pixels
+    pub fn width(&self) -> usize {
+
+        self.width
+    }
+
+    /// Returns the vertical resolution in pixels
+    pub fn height(&self) -> usize {
+
+        self.height
+    }
+
+    /// Returns the number of pixels in each row of the framebuffer, including any padding
+    pub fn stride(&self) -> usize {
+
+        self.stride
+    }
+
+    /// Returns the pixel format of the framebuffer
+    pub fn pixel_format(&self) -> PixelFormat {
+
+        self.pixel_format
+    }
+
+    /// Returns the number of bytes used by each pixel
+    pub fn bytes_per_pixel(&self) -> usize {
+
+        match self.pixel_format {
+            PixelFormat::BIT_MASK => {
+                let mask = self.pixel_information;
+                let bits = 32 - (mask.red_mask | mask.green_mask | mask.blue_mask |
+                                 mask.reserved_mask).leading_zeros();
+                ((bits + 7) / 8) as usize
+            },
+            _ => 4,
+        }
+    }
+
+    /// Returns the color of the pixel at the given location
+    pub fn read_pixel(&self, x: usize, y: usize) -> Option<BltPixel> {
+
+        self.offset(x, y).map(|offset| {
+            let mut value = 0u32;
+            for i in 0..self.bytes_per_pixel() {
+                let byte = unsafe { ptr::read_volatile(self.base.add(offset + i)) };
+                value |= (byte as u32) << (i * 8);
+            }
+            self.decode(value)
+        })
+    }
+
+    /// Sets the color of the pixel at the given location
+    ///
+    /// Locations outside the visible area are ignored.
+    pub fn write_pixel(&mut self, x: usize, y: usize, pixel: BltPixel) {
+
+        if let Some(offset) = self.offset(x, y) {
+            let value = self.encode(pixel);
+            for i in 0..self.bytes_per_pixel() {
+                unsafe { ptr::write_volatile(self.base.add(offset + i), (value >> (i * 8)) as u8) };
+            }
+        }
+    }
+
+    /// Fills a rectangle with a single color
+    ///
+    /// The rectangle is clipped to the visible area.
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, pixel: BltPixel) {
+
+        let x_end = cmp::min(x.saturating_add(width), self.width);
+        let y_end = cmp::min(y.saturating_add(height), self.height);
+        for row in y..y_end {
+            for column in x..x_end {
+                self.write_pixel(column, row, pixel);
+            }
+        }
+    }
+
+    /// Copies whole rows of pixels from one location to another
+    ///
+    /// The source and destination may overlap, which makes this suitable for scrolling. Rows
+    /// outside the visible area are not copied.
+    pub fn copy_rows(&mut self, source_y: usize, destination_y: usize, count: usize) {
+
+        let lower = cmp::max(source_y, destination_y);
+        let count = cmp::min(count, self.height.saturating_sub(lower));
+        if count == 0 {
+            return;
+        }
+
+        let row_size = self.stride * self.bytes_per_pixel();
+        let len = cmp::min(count * row_size, self.size.saturating_sub(lower * row_size));
+        unsafe {
+            ptr::copy(
+                self.base.add(source_y * row_size),
+                self.base.add(destination_y * row_size),
+                len
+            );
+        }
+    }
+
+    /// Converts a pixel into the framebuffer's format
+    pub fn encode(&self, pixel: BltPixel) -> u32 {
+
+        let (red, green, blue) = (pixel.red as u32, pixel.green as u32, pixel.blue as u32);
+        match self.pixel_format {
+            PixelFormat::RED_GREEN_BLUE_RESERVED_8_BIT_PER_COLOR => red | green << 8 | blue << 16,
+            PixelFormat::BLUE_GREEN_RED_RESERVED_8_BIT_PER_COLOR => blue | green << 8 | red << 16,
+            _ => {
+                let mask = self.pixel_information;
+                encode_channel(pixel.red, mask.red_mask) |
+                    encode_channel(pixel.green, mask.green_mask) |
+                    encode_channel(pixel.blue, mask.blue_mask)
+            },
+        }
+    }
+
+    /// Converts a pixel from the framebuffer's format
+    pub fn decode(&self, value: u32) -> BltPixel {
+
+        match self.pixel_format {
+            PixelFormat::RED_GREEN_BLUE_RESERVED_8_BIT_PER_COLOR =>
+                BltPixel::new(value as u8, (value >> 8) as u8, (value >> 16) as u8),
+            PixelFormat::BLUE_GREEN_RED_RESERVED_8_BIT_PER_COLOR =>
+                BltPixel::new((value >> 16) as u8, (value >> 8) as u8, value as u8),
+            _ => {
+                let mask = self.pixel_information;
+                BltPixel::new(
+                    decode_channel(value, mask.red_mask),
+                    decode_channel(value, mask.green_mask),
+                    decode_channel(value, mask.blue_mask)
+                )
+            },
+        }
+    }
+
+    /// Returns the byte offset of a pixel, if it is within the visible area
+    fn offset(&self, x: usize, y: usize) -> Option<usize> {
+
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let offset = y.checked_mul(self.stride)?
+            .checked_add(x)?
+            .checked_mul(bytes_per_pixel)?;
+        if offset.checked_add(bytes_per_pixel)? <= self.size {
+            Some(offset)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> fmt::Debug for FrameBuffer<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FrameBuffer")
+            .field("base", &self.base)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("stride", &self.stride)
+            .field("pixel_format", &self.pixel_format)
+            .finish()
+    }
+}
+
+
+/// Scales an 8-bit color channel into the bits selected by `mask`
+fn encode_channel(value: u8, mask: u32) -> u32 {
+
+    if mask == 0 {
+        return 0;
+    }
+
+    let bits = mask.count_ones();
+    let value = value as u32;
+    let scaled = if bits >= 8 { value << (bits - 8) } else { value >> (8 - bits) };
+    (scaled << mask.trailing_zeros()) & mask
+}
+
+
+/// Scales the bits selected by `mask` into an 8-bit color channel
+fn decode_channel(value: u32, mask: u32) -> u8 {
+
+    if mask == 0 {
+        return 0;
+    }
+
+    let bits = mask.count_ones();
+    let value = (value & mask) >> mask.trailing_zeros();
+    (if bits >= 8 { value >> (bits - 8) } else { value << (8 - bits) }) as u8
+}
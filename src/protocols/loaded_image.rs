@@ -0,0 +1,88 @@
+use core::{
+    fmt,
+    slice,
+};
+use {
+    boot_services::{
+        Guid,
+        Protocol,
+    },
+    types::{
+        Handle,
+        MemoryType,
+        Status,
+    },
+    SystemTable,
+};
+
+
+/// Describes an image which has been loaded into memory
+///
+/// An instance of this protocol is installed on the handle of every loaded image, including the
+/// image handle passed to `efi_main`.
+#[repr(C)]
+pub struct LoadedImage {
+    pub revision: u32,
+    pub parent_handle: Handle,
+    pub system_table: *const SystemTable,
+    pub device_handle: Handle,
+    pub file_path: *const (), // TODO
+    pub reserved: *const (),
+    pub load_options_size: u32,
+    pub load_options: *const u8,
+    pub image_base: *const u8,
+    pub image_size: u64,
+    pub image_code_type: MemoryType,
+    pub image_data_type: MemoryType,
+    pub _unload: extern "win64" fn(image_handle: Handle) -> Status,
+}
+
+impl LoadedImage {
+
+    /// Returns the raw load options which were passed to the image
+    pub fn load_options(&self) -> &[u8] {
+
+        if self.load_options.is_null() {
+            return &[];
+        }
+
+        unsafe { slice::from_raw_parts(self.load_options, self.load_options_size as usize) }
+    }
+}
+
+impl Protocol for LoadedImage {
+
+    fn guid() -> &'static Guid { &LOADED_IMAGE_GUID }
+}
+
+impl fmt::Debug for LoadedImage {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("LoadedImage")
+            .field("revision", &self.revision)
+            .field("parent_handle", &self.parent_handle)
+            .field("device_handle", &self.device_handle)
+            .field("image_base", &self.image_base)
+            .field("image_size", &self.image_size)
+            .field("image_code_type", &self.image_code_type)
+            .field("image_data_type", &self.image_data_type)
+            .finish()
+    }
+}
+
+
+/// Static Guid for LoadedImage
+static LOADED_IMAGE_GUID: Guid = Guid {
+    data_1: 0x5b1b31a1,
+    data_2: 0x9562,
+    data_3: 0x11d2,
+    data_4: [
+        0x8e,
+        0x3f,
+        0x00,
+        0xa0,
+        0xc9,
+        0x69,
+        0x72,
+        0x3b,
+    ],
+};
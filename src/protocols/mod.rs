@@ -3,9 +3,21 @@
 //! This module provides FFI-safe protocol definitions for the standard UEFI protocols.
 
 
+mod block;
 mod console;
+mod disk;
+mod driver;
 mod files;
+mod graphics;
+mod loaded_image;
+mod partition_info;
 
 
+pub use self::block::*;
 pub use self::console::*;
+pub use self::disk::*;
+pub use self::driver::*;
 pub use self::files::*;
+pub use self::graphics::*;
+pub use self::loaded_image::*;
+pub use self::partition_info::*;
@@ -0,0 +1,122 @@
+use core::fmt;
+use {
+    boot_services::{
+        Guid,
+        Protocol,
+    },
+    partition::{
+        GptPartition,
+        MbrPartition,
+    },
+};
+
+
+/// Describes the partition which a handle represents
+///
+/// Partition drivers install this protocol alongside `BlockIO` on each partition they create.
+#[repr(C, packed)]
+pub struct PartitionInfo {
+    pub revision: u32,
+    pub partition_type: PartitionType,
+    pub system: u8,
+    pub reserved: [u8; 7],
+    info: [u8; 128],
+}
+
+impl PartitionInfo {
+
+    /// Tells whether the partition is an EFI system partition
+    pub fn is_system(&self) -> bool {
+
+        self.system == 1
+    }
+
+    /// Returns the MBR partition record, if this is an MBR partition
+    pub fn mbr(&self) -> Option<MbrPartition> {
+
+        // Copy the field, since it can't be borrowed from a packed struct
+        let partition_type = self.partition_type;
+        match partition_type {
+            PartitionType::MBR => MbrPartition::parse(&self.info),
+            _ => None,
+        }
+    }
+
+    /// Returns the GPT partition entry, if this is a GPT partition
+    ///
+    /// The `index` of the returned entry is not known and is always 0.
+    pub fn gpt(&self) -> Option<GptPartition> {
+
+        let partition_type = self.partition_type;
+        match partition_type {
+            PartitionType::GPT => GptPartition::parse(0, &self.info),
+            _ => None,
+        }
+    }
+}
+
+impl Protocol for PartitionInfo {
+
+    fn guid() -> &'static Guid { &PARTITION_INFO_GUID }
+}
+
+impl fmt::Debug for PartitionInfo {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let revision = self.revision;
+        let partition_type = self.partition_type;
+        fmt.debug_struct("PartitionInfo")
+            .field("revision", &revision)
+            .field("partition_type", &partition_type)
+            .field("system", &self.system)
+            .field("mbr", &self.mbr())
+            .field("gpt", &self.gpt())
+            .finish()
+    }
+}
+
+
+/// Static Guid for PartitionInfo
+static PARTITION_INFO_GUID: Guid = Guid {
+    data_1: 0x8cf2f62c,
+    data_2: 0xbc9b,
+    data_3: 0x4821,
+    data_4: [
+        0x80,
+        0x8d,
+        0xec,
+        0x9e,
+        0xc4,
+        0x21,
+        0xa1,
+        0xa0,
+    ],
+};
+
+
+/// Revision of PartitionInfo defined by the UEFI specification
+pub const PARTITION_INFO_REVISION: u32 = 0x0000_1000;
+
+
+/// Kind of partition table which describes a partition
+///
+/// This is not an enum, since firmware may report values which are not defined here.
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct PartitionType(pub u32);
+
+impl PartitionType {
+    pub const OTHER: PartitionType = PartitionType(0);
+    pub const MBR: PartitionType = PartitionType(1);
+    pub const GPT: PartitionType = PartitionType(2);
+}
+
+impl fmt::Debug for PartitionType {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PartitionType::OTHER => fmt.write_str("Other"),
+            PartitionType::MBR => fmt.write_str("Mbr"),
+            PartitionType::GPT => fmt.write_str("Gpt"),
+            PartitionType(value) => fmt.debug_tuple("PartitionType").field(&value).finish(),
+        }
+    }
+}
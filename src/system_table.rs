@@ -8,6 +8,8 @@ use {
     },
 };
 #[cfg(feature = "boot-services")]
+use core::fmt;
+#[cfg(feature = "boot-services")]
 use {
     boot_services::BootServices,
     protocols::{
@@ -15,6 +17,7 @@ use {
         SimpleTextOutput,
     },
     types::Handle,
+    PrintTarget,
 };
 
 
@@ -49,6 +52,16 @@ pub struct SystemTable {
 }
 
 
+#[cfg(feature = "boot-services")]
+impl<'a> PrintTarget for &'a SystemTable {
+
+    fn print_fmt(self, args: fmt::Arguments) -> fmt::Result {
+
+        fmt::Write::write_fmt(&mut &*self.con_out, args)
+    }
+}
+
+
 /// Contains pointers to the runtime and boot services tables
 ///
 /// TODO:
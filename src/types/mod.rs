@@ -21,7 +21,7 @@ pub use self::memory::{
 
 
 /// Logical boolean
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(u8)]
 pub enum Bool {
     False = 0,
@@ -39,6 +39,17 @@ impl convert::From<bool> for Bool {
     }
 }
 
+impl convert::From<Bool> for bool {
+
+    fn from(b: Bool) -> Self {
+
+        match b {
+            Bool::False => false,
+            Bool::True => true,
+        }
+    }
+}
+
 
 /// 2-byte character
 pub type Char16 = u16;
@@ -81,6 +92,13 @@ impl<T> EfiBs<T> {
     pub(crate) fn is_null(&self) -> bool {
         self.0.as_ptr().is_null()
     }
+
+    /// Consumes the EfiBs, returning the wrapped raw pointer
+    ///
+    /// Unlike dereferencing, this preserves the pointer's permission to mutate its referent.
+    pub(crate) fn into_raw(self) -> *mut T {
+        self.0.as_ptr()
+    }
 }
 
 #[cfg(feature = "boot-services")]
@@ -202,5 +220,23 @@ pub struct TableHeader {
 }
 
 
+/// Calendar date and time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub pad_1: u8,
+    pub nanosecond: u32,
+    pub time_zone: i16,
+    pub daylight: u8,
+    pub pad_2: u8,
+}
+
+
 /// A virtual memory address
 pub type VirtualAddress = u64;
@@ -0,0 +1,175 @@
+//! Locating file system volumes
+//!
+//! A `Volume` pairs the root directory of a file system with the partition it resides on, if the
+//! firmware provides `PartitionInfo`. The most common use is finding the EFI system partition
+//! which the running image was loaded from.
+
+
+use {
+    boot_services::{
+        BootServices,
+        OpenProtocolAttributes,
+        Pool,
+        Protocol,
+        SearchType,
+    },
+    protocols::{
+        File,
+        LoadedImage,
+        OwnedFile,
+        PartitionInfo,
+        SimpleFileSystem,
+    },
+    types::{
+        EfiBs,
+        Handle,
+        Status,
+    },
+};
+
+
+/// An open file system volume
+///
+/// The root directory is closed when the volume is dropped.
+pub struct Volume {
+    handle: Handle,
+    root: OwnedFile,
+    partition_info: Option<EfiBs<PartitionInfo>>,
+}
+
+impl Volume {
+
+    /// Opens the file system on the given handle
+    ///
+    /// `agent_handle` is normally the image handle passed to `efi_main`.
+    pub fn open(
+        boot_services: &BootServices,
+        handle: Handle,
+        agent_handle: Handle
+    ) -> Result<Volume, Status> {
+
+        let file_system = boot_services.open_protocol::<SimpleFileSystem>(
+            handle,
+            agent_handle,
+            0,
+            OpenProtocolAttributes::GET_PROTOCOL
+        )?;
+        let root = OwnedFile::new(file_system.open_volume()?);
+
+        // Older firmware does not provide partition information
+        let partition_info = boot_services.open_protocol::<PartitionInfo>(
+            handle,
+            agent_handle,
+            0,
+            OpenProtocolAttributes::GET_PROTOCOL
+        ).ok();
+
+        Ok(Volume {
+            handle: handle,
+            root: root,
+            partition_info: partition_info,
+        })
+    }
+
+    /// Opens the volume which the given image was loaded from
+    pub fn boot_volume(
+        boot_services: &BootServices,
+        image_handle: Handle
+    ) -> Result<Volume, Status> {
+
+        let loaded_image = boot_services.open_protocol::<LoadedImage>(
+            image_handle,
+            image_handle,
+            0,
+            OpenProtocolAttributes::GET_PROTOCOL
+        )?;
+
+        Volume::open(boot_services, loaded_image.device_handle, image_handle)
+    }
+
+    /// Returns an iterator over all volumes with a supported file system
+    ///
+    /// Volumes which fail to open are skipped.
+    pub fn all<'a>(
+        boot_services: &'a BootServices,
+        agent_handle: Handle
+    ) -> Result<Volumes<'a>, Status> {
+
+        let guid = SimpleFileSystem::guid();
+        let handles = boot_services.locate_handle(SearchType::ByProtocol, Some(guid), None)?;
+        Ok(Volumes {
+            boot_services: boot_services,
+            agent_handle: agent_handle,
+            handles: handles,
+            index: 0,
+        })
+    }
+
+    /// Returns an iterator over all EFI system partitions with a supported file system
+    pub fn esps<'a>(
+        boot_services: &'a BootServices,
+        agent_handle: Handle
+    ) -> Result<impl Iterator<Item = Volume> + 'a, Status> {
+
+        Ok(Volume::all(boot_services, agent_handle)?.filter(|volume| volume.is_esp()))
+    }
+
+    /// Returns the handle which provides this volume's file system
+    pub fn handle(&self) -> Handle {
+
+        self.handle
+    }
+
+    /// Returns the root directory of the volume
+    pub fn root(&self) -> &File {
+
+        &self.root
+    }
+
+    /// Returns information about the partition containing the volume, if available
+    pub fn partition_info(&self) -> Option<&PartitionInfo> {
+
+        self.partition_info.as_ref().map(|info| &**info)
+    }
+
+    /// Tells whether the volume is an EFI system partition
+    ///
+    /// This is always false if the firmware does not provide partition information.
+    pub fn is_esp(&self) -> bool {
+
+        match self.partition_info() {
+            Some(info) => {
+                info.is_system() ||
+                    info.gpt().map_or(false, |partition| partition.is_esp()) ||
+                    info.mbr().map_or(false, |partition| partition.is_esp())
+            },
+            None => false,
+        }
+    }
+}
+
+
+/// Iterator over file system volumes
+pub struct Volumes<'a> {
+    boot_services: &'a BootServices,
+    agent_handle: Handle,
+    handles: Pool<'a, [Handle]>,
+    index: usize,
+}
+
+impl<'a> Iterator for Volumes<'a> {
+    type Item = Volume;
+
+    fn next(&mut self) -> Option<Volume> {
+
+        while self.index < self.handles.len() {
+            let handle = self.handles[self.index];
+            self.index += 1;
+            if let Ok(volume) = Volume::open(self.boot_services, handle, self.agent_handle) {
+                return Some(volume);
+            }
+        }
+
+        None
+    }
+}